@@ -0,0 +1,415 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Deserialize;
+
+const APP_NAME: &str = "enola";
+const ENV_PREFIX: &str = "ENOLA_";
+
+/// Options that choose the configuration itself and are never read from it.
+const RESERVED: [&str; 4] = ["help", "version", "config", "profile"];
+
+/// `config.toml`: named profiles of command line options, keyed by their long
+/// name (`connections = 3`, `user-agents = "~/ua.txt"`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    /// Profile applied when `--profile` is not given.
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+/// Where the value of an option comes from, by decreasing precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    CommandLine,
+    Env(String),
+    Profile(String),
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::CommandLine => write!(f, "command line"),
+            Source::Env(var) => write!(f, "env {}", var),
+            Source::Profile(name) => write!(f, "profile {}", name),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/enola/config.toml`, `$XDG_CONFIG_HOME` defaulting to
+/// `~/.config` on every platform.
+pub fn config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")));
+    config_home.map(|dir| dir.join(APP_NAME).join("config.toml"))
+}
+
+/// Reads a configuration file. A missing file is an empty configuration
+/// unless `required`.
+pub fn load(path: &Path, required: bool) -> Result<Config, String> {
+    if !path.exists() && !required {
+        return Ok(Config::default());
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&text).map_err(|e| format!("Invalid configuration {}: {}", path.display(), e))
+}
+
+impl Config {
+    /// The options of a profile, `None` meaning no profile at all.
+    pub fn profile(&self, name: Option<&str>) -> Result<Option<(&str, &toml::Table)>, String> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            return Ok(None);
+        };
+        match self.profiles.get_key_value(name) {
+            Some((name, options)) => Ok(Some((name.as_str(), options))),
+            None => Err(format!(
+                "Unknown profile \"{}\" (available: {})",
+                name,
+                if self.profiles.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+                }
+            )),
+        }
+    }
+}
+
+/// Environment variable setting an option: `ENOLA_` and its long name in
+/// upper snake case, e.g. `ENOLA_USER_AGENTS`.
+pub fn env_var(arg: &Arg) -> Option<String> {
+    arg.get_long()
+        .map(|long| format!("{}{}", ENV_PREFIX, long.to_uppercase().replace('-', "_")))
+}
+
+/// Top-level options that can be set by the environment or a profile.
+fn configurable(command: &Command) -> impl Iterator<Item = &Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| arg.get_long().is_some() && !RESERVED.contains(&arg.get_id().as_str()))
+}
+
+fn is_flag(arg: &Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::SetTrue)
+}
+
+fn expand_home(value: &str) -> String {
+    match (value.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => value.to_string(),
+    }
+}
+
+/// Command line arguments giving `arg` a configuration value.
+fn profile_args(arg: &Arg, value: &toml::Value) -> Result<Vec<OsString>, String> {
+    let long = arg.get_long().unwrap_or_default();
+    let scalar = |value: &toml::Value| -> Result<Option<String>, String> {
+        match value {
+            toml::Value::String(value) => Ok(Some(expand_home(value))),
+            toml::Value::Integer(value) => Ok(Some(value.to_string())),
+            toml::Value::Float(value) => Ok(Some(value.to_string())),
+            toml::Value::Boolean(true) if !is_flag(arg) => Ok(None),
+            _ => Err(format!("Invalid value for \"{}\": {}", long, value)),
+        }
+    };
+    let values = match value {
+        toml::Value::Boolean(true) if is_flag(arg) => return Ok(vec![format!("--{}", long).into()]),
+        toml::Value::Boolean(false) if is_flag(arg) => return Ok(Vec::new()),
+        toml::Value::Array(values) => values.iter().map(scalar).collect::<Result<Vec<_>, _>>()?,
+        value => vec![scalar(value)?],
+    };
+    Ok(values
+        .into_iter()
+        .map(|value| match value {
+            Some(value) => format!("--{}={}", long, value).into(),
+            None => format!("--{}", long).into(),
+        })
+        .collect())
+}
+
+fn env_args(arg: &Arg, value: &str) -> Vec<OsString> {
+    let long = arg.get_long().unwrap_or_default();
+    if is_flag(arg) {
+        return match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => vec![format!("--{}", long).into()],
+            _ => Vec::new(),
+        };
+    }
+    vec![format!("--{}={}", long, value).into()]
+}
+
+/// Whether clap refuses `a` and `b` together, whichever declared the conflict.
+fn conflicts(command: &Command, a: &Arg, b: &Arg) -> bool {
+    command.get_arg_conflicts_with(a).iter().any(|arg| arg.get_id() == b.get_id())
+        || command.get_arg_conflicts_with(b).iter().any(|arg| arg.get_id() == a.get_id())
+}
+
+/// Fills the options missing from the command line from the environment,
+/// then from a profile, and returns the resulting command line with where
+/// each filled option comes from.
+///
+/// `matches` comes from a lenient parse of `args`, only used to tell which
+/// options were given, and `env` looks up environment variables. An option
+/// conflicting with one given by a source of higher precedence is left out,
+/// so that `ENOLA_YES=1` does not break `--no-input`.
+pub fn apply(
+    command: &Command,
+    matches: &ArgMatches,
+    mut args: Vec<OsString>,
+    profile: Option<(&str, &toml::Table)>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<(Vec<OsString>, BTreeMap<String, Source>), String> {
+    if let Some((name, options)) = profile {
+        for key in options.keys() {
+            let known = configurable(command).any(|arg| {
+                arg.get_long() == Some(key.as_str()) || arg.get_id().as_str() == key.replace('-', "_")
+            });
+            if !known {
+                return Err(format!("Unknown option \"{}\" in profile {}", key, name));
+            }
+        }
+    }
+
+    let mut decided: BTreeSet<&str> = BTreeSet::new();
+    let mut given: Vec<&Arg> = Vec::new();
+    for arg in configurable(command) {
+        if matches!(matches.value_source(arg.get_id().as_str()), Some(ValueSource::CommandLine)) {
+            decided.insert(arg.get_id().as_str());
+            given.push(arg);
+        }
+    }
+
+    let mut extra = Vec::new();
+    let mut sources = BTreeMap::new();
+    for arg in configurable(command) {
+        let id = arg.get_id().as_str();
+        if decided.contains(id) || given.iter().any(|other| conflicts(command, arg, other)) {
+            continue;
+        }
+        if let Some(var) = env_var(arg)
+            && let Some(value) = env(&var)
+        {
+            let values = env_args(arg, &value);
+            if !values.is_empty() {
+                given.push(arg);
+            }
+            extra.extend(values);
+            decided.insert(id);
+            sources.insert(id.to_string(), Source::Env(var));
+        }
+    }
+
+    if let Some((name, options)) = profile {
+        for arg in configurable(command) {
+            let id = arg.get_id().as_str();
+            if decided.contains(id) || given.iter().any(|other| conflicts(command, arg, other)) {
+                continue;
+            }
+            let long = arg.get_long().unwrap_or_default();
+            if let Some(value) = options.get(long).or_else(|| options.get(id)) {
+                let values = profile_args(arg, value)?;
+                if !values.is_empty() {
+                    given.push(arg);
+                }
+                extra.extend(values);
+                sources.insert(id.to_string(), Source::Profile(name.to_string()));
+            }
+        }
+    }
+
+    let at = 1.min(args.len());
+    args.splice(at..at, extra);
+    Ok((args, sources))
+}
+
+/// Every configurable option with its effective value and source.
+pub fn effective(
+    command: &Command,
+    matches: &ArgMatches,
+    sources: &BTreeMap<String, Source>,
+) -> Vec<(String, String, Source)> {
+    configurable(command)
+        .map(|arg| {
+            let id = arg.get_id().as_str();
+            let value = matches
+                .get_raw(id)
+                .map(|values| values.map(|value| value.to_string_lossy().into_owned()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            let source = match (sources.get(id), matches.value_source(id)) {
+                (Some(source), _) => source.clone(),
+                (None, Some(ValueSource::CommandLine)) => Source::CommandLine,
+                _ => Source::Default,
+            };
+            let value = match value.as_slice() {
+                [] if matches.value_source(id).is_some() => "(set)".to_string(),
+                [] => "(unset)".to_string(),
+                [value] => value.clone(),
+                values => values.join(", "),
+            };
+            (arg.get_long().unwrap_or(id).to_string(), value, source)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command() -> Command {
+        Command::new("enola")
+            .arg(Arg::new("config").long("config"))
+            .arg(Arg::new("connections").long("connections").default_value("5"))
+            .arg(Arg::new("user_agents").long("user-agents"))
+            .arg(Arg::new("engines").long("engines").action(ArgAction::Append))
+            .arg(Arg::new("yes").long("yes").action(ArgAction::SetTrue))
+            .arg(Arg::new("no_input").long("no-input").action(ArgAction::SetTrue).conflicts_with("yes"))
+            .arg(Arg::new("target").short('u'))
+    }
+
+    fn profile(text: &str) -> toml::Table {
+        toml::from_str(text).unwrap()
+    }
+
+    /// Applies the environment and profile to a command line, then parses the
+    /// result strictly.
+    fn run(
+        line: &[&str],
+        env: &[(&str, &str)],
+        profile: Option<&toml::Table>,
+    ) -> Result<(ArgMatches, BTreeMap<String, Source>), String> {
+        let command = command();
+        let args: Vec<OsString> = line.iter().map(OsString::from).collect();
+        let lenient = command.clone().ignore_errors(true).try_get_matches_from(&args).unwrap();
+        let env: BTreeMap<String, String> = env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let (args, sources) = apply(&command, &lenient, args, profile.map(|options| ("ci", options)), |var| {
+            env.get(var).cloned()
+        })?;
+        let matches = command.try_get_matches_from(args).map_err(|e| e.to_string())?;
+        Ok((matches, sources))
+    }
+
+    fn arg(id: &str) -> Arg {
+        command().get_arguments().find(|arg| arg.get_id() == id).unwrap().clone()
+    }
+
+    #[test]
+    fn names_variables_after_long_options() {
+        assert_eq!(env_var(&arg("user_agents")).as_deref(), Some("ENOLA_USER_AGENTS"));
+        assert_eq!(env_var(&arg("target")), None);
+    }
+
+    #[test]
+    fn turns_environment_values_into_arguments() {
+        assert_eq!(env_args(&arg("connections"), "3"), ["--connections=3"]);
+        assert_eq!(env_args(&arg("yes"), "TRUE"), ["--yes"]);
+        assert_eq!(env_args(&arg("yes"), "on"), ["--yes"]);
+        assert!(env_args(&arg("yes"), "0").is_empty());
+    }
+
+    #[test]
+    fn turns_profile_values_into_arguments() {
+        let value = |text: &str| profile(&format!("v = {}", text)).remove("v").unwrap();
+        assert_eq!(profile_args(&arg("connections"), &value("3")), Ok(vec!["--connections=3".into()]));
+        assert_eq!(profile_args(&arg("connections"), &value("\"3\"")), Ok(vec!["--connections=3".into()]));
+        assert_eq!(profile_args(&arg("yes"), &value("true")), Ok(vec!["--yes".into()]));
+        assert_eq!(profile_args(&arg("yes"), &value("false")), Ok(Vec::new()));
+        assert_eq!(
+            profile_args(&arg("engines"), &value("[\"bing\", \"yandex\"]")),
+            Ok(vec!["--engines=bing".into(), "--engines=yandex".into()])
+        );
+        assert!(profile_args(&arg("connections"), &value("false")).is_err());
+        assert!(profile_args(&arg("engines"), &value("[[1]]")).is_err());
+
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            profile_args(&arg("user_agents"), &value("\"~/ua.txt\"")),
+            Ok(vec![format!("--user-agents={}", home.join("ua.txt").display()).into()])
+        );
+    }
+
+    #[test]
+    fn command_line_wins_over_environment_over_profile_over_default() {
+        let options = profile("connections = 1\nuser-agents = \"/profile.txt\"\nengines = [\"bing\"]");
+        let env = [("ENOLA_CONNECTIONS", "2"), ("ENOLA_USER_AGENTS", "/env.txt")];
+
+        let (matches, sources) = run(&["enola", "--connections", "3"], &env, Some(&options)).unwrap();
+        assert_eq!(matches.get_one::<String>("connections").unwrap(), "3");
+        assert_eq!(matches.get_one::<String>("user_agents").unwrap(), "/env.txt");
+        assert_eq!(matches.get_many::<String>("engines").unwrap().collect::<Vec<_>>(), ["bing"]);
+        assert_eq!(sources.get("connections"), None);
+        assert_eq!(sources.get("user_agents"), Some(&Source::Env("ENOLA_USER_AGENTS".to_string())));
+        assert_eq!(sources.get("engines"), Some(&Source::Profile("ci".to_string())));
+
+        let (matches, _) = run(&["enola"], &env, Some(&options)).unwrap();
+        assert_eq!(matches.get_one::<String>("connections").unwrap(), "2");
+        let (matches, _) = run(&["enola"], &[], Some(&options)).unwrap();
+        assert_eq!(matches.get_one::<String>("connections").unwrap(), "1");
+        let (matches, sources) = run(&["enola"], &[], None).unwrap();
+        assert_eq!(matches.get_one::<String>("connections").unwrap(), "5");
+        assert!(sources.is_empty());
+    }
+
+    #[test]
+    fn command_line_lists_replace_profile_lists() {
+        let options = profile("engines = [\"bing\", \"yandex\"]");
+        let (matches, _) = run(&["enola", "--engines", "google"], &[], Some(&options)).unwrap();
+        assert_eq!(matches.get_many::<String>("engines").unwrap().collect::<Vec<_>>(), ["google"]);
+    }
+
+    #[test]
+    fn flags_from_the_environment_or_a_profile() {
+        let (matches, _) = run(&["enola"], &[("ENOLA_YES", "1")], None).unwrap();
+        assert!(matches.get_flag("yes"));
+        let (matches, sources) = run(&["enola"], &[("ENOLA_YES", "0")], Some(&profile("yes = true"))).unwrap();
+        assert!(!matches.get_flag("yes"));
+        assert_eq!(sources.get("yes"), Some(&Source::Env("ENOLA_YES".to_string())));
+    }
+
+    #[test]
+    fn skips_options_conflicting_with_higher_precedence_ones() {
+        let (matches, sources) = run(&["enola", "--no-input"], &[("ENOLA_YES", "1")], None).unwrap();
+        assert!(matches.get_flag("no_input") && !matches.get_flag("yes"));
+        assert_eq!(sources.get("yes"), None);
+
+        let (matches, _) = run(&["enola", "--no-input"], &[], Some(&profile("yes = true"))).unwrap();
+        assert!(matches.get_flag("no_input") && !matches.get_flag("yes"));
+
+        let (matches, _) = run(&["enola"], &[("ENOLA_NO_INPUT", "true")], Some(&profile("yes = true"))).unwrap();
+        assert!(matches.get_flag("no_input") && !matches.get_flag("yes"));
+
+        let (matches, _) = run(&["enola", "--yes"], &[("ENOLA_NO_INPUT", "0")], Some(&profile("no-input = true"))).unwrap();
+        assert!(matches.get_flag("yes") && !matches.get_flag("no_input"));
+    }
+
+    #[test]
+    fn rejects_unknown_profile_keys() {
+        assert_eq!(
+            run(&["enola"], &[], Some(&profile("colour = true"))).err(),
+            Some("Unknown option \"colour\" in profile ci".to_string())
+        );
+        assert!(run(&["enola"], &[], Some(&profile("config = \"/x.toml\""))).is_err());
+        assert!(run(&["enola"], &[], Some(&profile("user_agents = \"/ua.txt\""))).is_ok());
+    }
+
+    #[test]
+    fn looks_up_profiles() {
+        let config: Config = toml::from_str("default-profile = \"ci\"\n[profiles.ci]\nyes = true\n[profiles.dev]\n").unwrap();
+        assert_eq!(config.profile(None).unwrap().map(|(name, _)| name), Some("ci"));
+        assert_eq!(config.profile(Some("dev")).unwrap().map(|(name, _)| name), Some("dev"));
+        assert_eq!(config.profile(Some("prod")).err(), Some("Unknown profile \"prod\" (available: ci, dev)".to_string()));
+        assert!(Config::default().profile(None).unwrap().is_none());
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::Stream;
+use tokio::sync::mpsc;
+
+use crate::core::finding::{Finding, ResponseMeta};
+use crate::core::proxy::PoolHealth;
+use crate::core::search::ResponseKind;
+
+/// Something that happened during a scan, in the order it happened.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Sites left out for a target because it breaks their username rules,
+    /// as `(site, reason)`.
+    SitesSkipped { target: String, sites: Vec<(String, String)> },
+    /// A site request could not be built for a target.
+    SiteInvalid { target: String, site: String, error: String },
+    /// A request is about to be sent, through `proxy` in dork mode.
+    RequestSent { target: String, url: String, proxy: Option<String> },
+    /// A response, or why a request failed.
+    ResponseReceived(Box<ResponseMeta>),
+    /// A checked profile, whatever its verdict, or a new search result.
+    Finding(Box<Finding>),
+    /// A dork mode worker fetched a result page.
+    PageFetched { worker: usize, proxy: String, url: String, kind: ResponseKind },
+    /// A search page brought no new result; `seen` tells whether it had
+    /// results already found on a previous page.
+    PageEmpty { target: String, engine: &'static str, query: String, page: usize, seen: bool },
+    /// A proxy failed to fetch a search page.
+    ProxyFailed { worker: usize, proxy: String, url: String, reason: String },
+    /// A failed search page is queued again after `delay`.
+    QueryRetried { target: String, query: String, url: String, attempt: usize, delay: Duration },
+    /// A search page was given up on.
+    QueryAbandoned { target: String, engine: &'static str, query: String, page: usize, url: String, reason: String },
+    /// Requests (API mode) or pages (dork mode) done and still to do.
+    Progress { done: usize, remaining: usize },
+    /// A failure that does not stop the scan.
+    Error(String),
+    /// No request is sent and no finding is made after this event.
+    Stopped,
+    /// A target is done and its results are written.
+    TargetFinished(Box<TargetSummary>),
+    /// The scan is over. Always the last event.
+    RunFinished(Box<Summary>),
+}
+
+/// What a scan found for one target.
+#[derive(Debug, Clone, Default)]
+pub struct TargetSummary {
+    pub target: String,
+    /// Profiles found (API mode) or new search results (dork mode).
+    pub found: usize,
+    pub not_found: usize,
+    pub uncertain: usize,
+    /// Results file and number of findings written to it, with an output.
+    pub results: Option<(PathBuf, usize)>,
+    /// `(engine, query, page)` of every query that exhausted its retries.
+    pub dead_letters: Vec<(&'static str, String, usize)>,
+    /// Where the dead letters' queries were saved, with an output.
+    pub dead_letters_path: Option<PathBuf>,
+    /// `(engine, query, pages, links)` of every query with a fetched page.
+    pub pages: Vec<(&'static str, String, usize, usize)>,
+}
+
+/// How a scan went as a whole.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub duration: Duration,
+    /// Responses received, failed requests included.
+    pub requests: usize,
+    /// Requests (API mode) or pages (dork mode) done.
+    pub done: usize,
+    /// Pages given up on after their retries.
+    pub abandoned: usize,
+    /// Requests or pages left when the scan was cancelled.
+    pub cancelled: usize,
+    /// Search responses by kind, in dork mode.
+    pub responses: Vec<(ResponseKind, usize)>,
+    /// State of the proxy pool at the end, in dork mode.
+    pub pool: Option<PoolHealth>,
+}
+
+/// Hands every event to every subscriber, in the order they were emitted.
+///
+/// Subscribers read from unbounded queues, so a slow one, like a results file,
+/// never misses a finding nor holds the scan back. A subscription ends once
+/// every copy of the bus is dropped.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<Event>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events emitted from now on.
+    pub fn subscribe(&self) -> Subscription {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap().push(tx);
+        Subscription { rx }
+    }
+
+    pub fn emit(&self, event: Event) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+/// Events of a bus, read one at a time or as a stream.
+pub struct Subscription {
+    rx: mpsc::UnboundedReceiver<Event>,
+}
+
+impl Subscription {
+    pub async fn recv(&mut self) -> Option<Event> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        self.rx.poll_recv(cx)
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use crate::core::request::{Detection, Verdict};
+use crate::core::search::{Job, SearchResult};
+use crate::core::sites::Site;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Api,
+    Dork,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Api => "api",
+            Mode::Dork => "dork",
+        })
+    }
+}
+
+/// A single result of a scan, in either mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub target: String,
+    pub mode: Mode,
+    /// Site name in API mode, search engine in dork mode.
+    pub source: String,
+    /// Category of the site, in API mode.
+    #[serde(default)]
+    pub category: Option<String>,
+    pub query: Option<String>,
+    pub url: String,
+    pub title: Option<String>,
+    pub snippet: Option<String>,
+    pub status: Option<u16>,
+    pub verdict: Option<Verdict>,
+    pub reason: Option<String>,
+    pub timestamp: String,
+}
+
+/// What a single request got back, kept alongside the findings of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseMeta {
+    #[serde(default)]
+    pub target: String,
+    /// Site name in API mode, search engine in dork mode.
+    pub source: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    pub query: Option<String>,
+    pub url: String,
+    pub proxy: Option<String>,
+    pub status: Option<u16>,
+    /// Verdict in API mode, response kind in dork mode, or the request error.
+    pub outcome: String,
+    /// Verdict on the profile, in API mode.
+    #[serde(default)]
+    pub verdict: Option<Verdict>,
+    pub length: Option<u64>,
+    pub elapsed_ms: u64,
+    pub timestamp: String,
+}
+
+impl ResponseMeta {
+    pub fn new(target: &str, source: &str, url: &str, status: Option<u16>, outcome: String, elapsed: Duration) -> Self {
+        Self {
+            target: target.to_string(),
+            source: source.to_string(),
+            category: None,
+            query: None,
+            url: url.to_string(),
+            proxy: None,
+            status,
+            outcome,
+            verdict: None,
+            length: None,
+            elapsed_ms: elapsed.as_millis() as u64,
+            timestamp: now(),
+        }
+    }
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn non_empty(text: String) -> Option<String> {
+    if text.is_empty() { None } else { Some(text) }
+}
+
+impl Finding {
+    /// A profile checked in API mode.
+    pub fn profile(target: &str, site: &Site, url: &str, status: Option<u16>, detection: &Detection) -> Self {
+        Self {
+            target: target.to_string(),
+            mode: Mode::Api,
+            source: site.name.clone(),
+            category: site.category.clone(),
+            query: None,
+            url: url.to_string(),
+            title: None,
+            snippet: None,
+            status,
+            verdict: Some(detection.verdict),
+            reason: Some(detection.rule.clone()),
+            timestamp: now(),
+        }
+    }
+
+    /// A search result found in dork mode.
+    pub fn search(job: &Job, result: SearchResult) -> Self {
+        let (title, link, description) = result;
+        Self {
+            target: job.target.clone(),
+            mode: Mode::Dork,
+            source: job.engine.name().to_string(),
+            category: None,
+            query: Some(job.query.clone()),
+            url: link,
+            title: non_empty(title),
+            snippet: non_empty(description),
+            status: None,
+            verdict: None,
+            reason: None,
+            timestamp: now(),
+        }
+    }
+}
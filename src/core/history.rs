@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, create_dir_all};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::finding::{Finding, Mode, ResponseMeta};
+use crate::core::logger::Logger;
+use crate::core::request::Verdict;
+use crate::core::save::{runs_dir, target_dir};
+
+const RUN_EXTENSION: &str = ".run.json";
+
+/// A finished scan of a target, kept next to its results so that later
+/// scans can be compared against it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    pub target: String,
+    pub mode: Mode,
+    pub started: String,
+    pub finished: String,
+    pub findings: Vec<Finding>,
+    #[serde(default)]
+    pub responses: Vec<ResponseMeta>,
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl Run {
+    pub fn new(id: &str, target: &str, mode: Mode) -> Self {
+        Self {
+            id: id.to_string(),
+            target: target.to_string(),
+            mode,
+            started: now(),
+            finished: String::new(),
+            findings: Vec::new(),
+            responses: Vec::new(),
+        }
+    }
+
+    pub fn finish(&mut self, findings: Vec<Finding>, responses: Vec<ResponseMeta>) {
+        self.finished = now();
+        self.findings = findings;
+        self.responses = responses;
+    }
+}
+
+fn run_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}{}", id, RUN_EXTENSION))
+}
+
+/// Writes `<target dir>/<run id>.run.json`.
+pub fn save_run(logger: &Logger, run: &Run, output_dir: Option<&Path>) -> Result<PathBuf, Error> {
+    let dir = target_dir(logger, &run.target, output_dir);
+    create_dir_all(&dir)?;
+    let path = run_path(&dir, &run.id);
+    let mut file = BufWriter::new(File::create(&path)?);
+    serde_json::to_writer_pretty(&mut file, run)?;
+    writeln!(file)?;
+    file.flush()?;
+    logger.dbg(&format!("Run {} recorded → {}", run.id, path.display()), false);
+    Ok(path)
+}
+
+/// Ids of every recorded run of a target, oldest first.
+pub fn list_runs(logger: &Logger, target: &str, output_dir: Option<&Path>) -> Result<Vec<String>, Error> {
+    let dir = target_dir(logger, target, output_dir);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut ids: Vec<String> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(RUN_EXTENSION))
+                .map(str::to_string)
+        })
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+fn read_run(path: &Path) -> Result<Run, Error> {
+    let file = File::open(path).map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+pub fn load_run(logger: &Logger, target: &str, id: &str, output_dir: Option<&Path>) -> Result<Run, Error> {
+    read_run(&run_path(&target_dir(logger, target, output_dir), id))
+}
+
+/// Every recorded run with this id, whatever its target.
+pub fn find_runs(logger: &Logger, id: &str, output_dir: Option<&Path>) -> Result<Vec<Run>, Error> {
+    let dir = runs_dir(logger, output_dir);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut runs = Vec::new();
+    for entry in fs::read_dir(&dir)?.filter_map(|entry| entry.ok()) {
+        let path = run_path(&entry.path(), id);
+        if path.is_file() {
+            runs.push(read_run(&path)?);
+        }
+    }
+    runs.sort_by(|a, b| a.target.cmp(&b.target));
+    Ok(runs)
+}
+
+/// What changed between two runs, findings being matched by URL.
+#[derive(Debug, Default)]
+pub struct Diff {
+    /// Search results and profiles found only in the new run.
+    pub added: Vec<Finding>,
+    /// Search results and profiles found only in the old run.
+    pub removed: Vec<Finding>,
+    /// Findings present in both runs whose verdict, status or title differ,
+    /// as `(old, new)`.
+    pub changed: Vec<(Finding, Finding)>,
+    pub unchanged: usize,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Whether a finding is a search result or a profile found, as opposed to a
+/// site where the target was not found or could not be told apart.
+fn is_found(finding: &Finding) -> bool {
+    finding.verdict.is_none_or(|verdict| verdict == Verdict::Found)
+}
+
+/// Compares two runs. A site checked in only one of them counts as added or
+/// removed only when the profile was found there.
+pub fn diff(old: &Run, new: &Run) -> Diff {
+    let mut previous: BTreeMap<&str, &Finding> = old.findings.iter().map(|f| (f.url.as_str(), f)).collect();
+    let mut result = Diff::default();
+
+    for finding in &new.findings {
+        match previous.remove(finding.url.as_str()) {
+            None if is_found(finding) => result.added.push(finding.clone()),
+            None => {}
+            Some(before)
+                if before.verdict != finding.verdict
+                    || before.status != finding.status
+                    || before.title != finding.title =>
+            {
+                result.changed.push((before.clone(), finding.clone()));
+            }
+            Some(_) => result.unchanged += 1,
+        }
+    }
+    result.removed = previous.into_values().filter(|finding| is_found(finding)).cloned().collect();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(url: &str, verdict: Verdict, status: u16) -> Finding {
+        Finding {
+            target: "foo".to_string(),
+            mode: Mode::Api,
+            source: "example".to_string(),
+            category: None,
+            query: None,
+            url: url.to_string(),
+            title: None,
+            snippet: None,
+            status: Some(status),
+            verdict: Some(verdict),
+            reason: Some("status=200".to_string()),
+            timestamp: "2025-01-31T23:59:59Z".to_string(),
+        }
+    }
+
+    fn run(id: &str, findings: Vec<Finding>) -> Run {
+        let mut run = Run::new(id, "foo", Mode::Api);
+        run.finish(findings, Vec::new());
+        run
+    }
+
+    fn urls(findings: &[Finding]) -> Vec<&str> {
+        findings.iter().map(|finding| finding.url.as_str()).collect()
+    }
+
+    #[test]
+    fn lists_added_removed_and_unchanged_findings() {
+        let old = run("1", vec![profile("https://a", Verdict::Found, 200), profile("https://b", Verdict::Found, 200)]);
+        let new = run("2", vec![profile("https://b", Verdict::Found, 200), profile("https://c", Verdict::Found, 200)]);
+        let changes = diff(&old, &new);
+        assert_eq!(urls(&changes.added), ["https://c"]);
+        assert_eq!(urls(&changes.removed), ["https://a"]);
+        assert!(changes.changed.is_empty());
+        assert_eq!(changes.unchanged, 1);
+        assert!(!changes.is_empty());
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn reports_verdict_status_and_title_changes() {
+        let old = run(
+            "1",
+            vec![
+                profile("https://a", Verdict::Found, 200),
+                profile("https://b", Verdict::Found, 200),
+                Finding { title: Some("Old".to_string()), ..profile("https://c", Verdict::Found, 200) },
+            ],
+        );
+        let new = run(
+            "2",
+            vec![
+                profile("https://a", Verdict::NotFound, 200),
+                profile("https://b", Verdict::Found, 301),
+                Finding { title: Some("New".to_string()), ..profile("https://c", Verdict::Found, 200) },
+            ],
+        );
+        let changes = diff(&old, &new);
+        assert_eq!(changes.changed.len(), 3);
+        let (before, after) = &changes.changed[0];
+        assert_eq!((before.verdict, after.verdict), (Some(Verdict::Found), Some(Verdict::NotFound)));
+        assert!(changes.added.is_empty() && changes.removed.is_empty());
+    }
+
+    #[test]
+    fn ignores_sites_without_a_profile_in_only_one_run() {
+        let old = run("1", vec![profile("https://a", Verdict::NotFound, 404)]);
+        let new = run("2", vec![profile("https://b", Verdict::Uncertain, 429)]);
+        assert!(diff(&old, &new).is_empty());
+    }
+}
@@ -44,19 +44,48 @@ impl From<u8> for LogLevel {
 }
 
 
-pub(crate) struct Logger {
+/// How confirmation prompts are answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Prompts {
+    /// Ask on stdin.
+    #[default]
+    Ask,
+    /// Answer yes to every prompt.
+    Yes,
+    /// Never ask and take the default answer of every prompt.
+    Defaults,
+}
+
+pub struct Logger {
     level: LogLevel,
     output: Mutex<Box<dyn Write + Send>>,
+    prompts: Prompts,
 }
 
 impl Logger {
     pub fn new(level: LogLevel) -> Self {
         Logger {
             level,
-            output: Mutex::new(Box::new(io::stdout()))
+            output: Mutex::new(Box::new(io::stdout())),
+            prompts: Prompts::Ask,
+        }
+    }
+
+    /// A logger writing somewhere else than stdout, e.g. `io::sink()` to keep
+    /// a library quiet.
+    pub fn with_output(level: LogLevel, output: Box<dyn Write + Send>) -> Self {
+        Logger {
+            level,
+            output: Mutex::new(output),
+            prompts: Prompts::Ask,
         }
     }
 
+    pub fn with_prompts(mut self, prompts: Prompts) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
     pub fn log(&self, level: LogLevel, message: &str, bold: bool) {
         if level <= self.level {
             let mut out = self.output.lock().unwrap();
@@ -85,4 +114,57 @@ impl Logger {
         io::stdin().read_line(&mut input).expect("Failed to read line");
         input.trim().to_string()
     }
+
+    /// Asks a yes/no question; an empty answer, or no question at all when
+    /// prompts are off, gives `default`.
+    pub fn confirm(&self, prompt: &str, default: bool) -> bool {
+        self.confirm_or(prompt, default, default)
+    }
+
+    /// Like [`Logger::confirm`], but answers `unattended` when prompts are
+    /// off, for questions whose safe answer differs from the convenient one.
+    pub fn confirm_or(&self, prompt: &str, default: bool, unattended: bool) -> bool {
+        let choices = if default { "[Y/n]" } else { "[y/N]" };
+        match self.prompts {
+            Prompts::Ask => {
+                let input = self.input(&format!("{} {}", prompt, choices)).to_lowercase();
+                if input.is_empty() { default } else { input.starts_with('y') }
+            }
+            Prompts::Yes => {
+                self.inf(&format!("{} {} yes (--yes)", prompt, choices), false);
+                true
+            }
+            Prompts::Defaults => {
+                let answer = if unattended { "yes" } else { "no" };
+                self.warn(&format!("{} {} {} (non-interactive)", prompt, choices, answer), false);
+                unattended
+            }
+        }
+    }
+}
+
+/// Human readable duration: `1 hour, 2 minutes, and 3 seconds`.
+pub fn time_format(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    let secs = seconds % 60;
+
+    let mut parts = Vec::new();
+    if hours > 0 {
+        parts.push(format!("{} hour{}", hours, if hours > 1 { "s" } else { "" }));
+    }
+    if minutes > 0 {
+        parts.push(format!("{} minute{}", minutes, if minutes > 1 { "s" } else { "" }));
+    }
+    if secs > 0 || parts.is_empty() {
+        parts.push(format!("{} second{}", secs, if secs > 1 { "s" } else { "" }));
+    }
+
+    if parts.len() > 1 {
+        let last = parts.len() - 1;
+        let value = std::mem::take(&mut parts[last]);
+        parts[last] = format!("and {}", value);
+    }
+
+    parts.join(", ")
 }
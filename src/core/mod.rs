@@ -1,5 +1,18 @@
 pub mod logger;
+pub mod config;
 pub mod request;
 pub mod query;
 pub mod proxy;
+pub mod ratelimit;
 pub mod save;
+pub mod sites;
+pub mod search;
+pub mod progress;
+pub mod finding;
+pub mod event;
+pub mod history;
+pub mod report;
+pub mod permute;
+pub mod scanner;
+#[cfg(feature = "sqlite")]
+pub mod store;
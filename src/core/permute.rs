@@ -0,0 +1,204 @@
+/// How candidate usernames are derived from a name.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Strings put between name parts. Parts are always joined without
+    /// separator as well.
+    pub separators: Vec<String>,
+    /// Adds a leetspeak variant (`john` → `j0hn`) of every candidate.
+    pub leet: bool,
+    /// Appended to every candidate, e.g. birth years.
+    pub suffixes: Vec<String>,
+    /// Shortens name parts to their initial (`jdoe`, `johnd`).
+    pub truncate: bool,
+    /// Maximum number of candidates, the name itself included.
+    pub limit: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            separators: vec![".".to_string(), "_".to_string(), "-".to_string()],
+            leet: false,
+            suffixes: Vec::new(),
+            truncate: true,
+            limit: 100,
+        }
+    }
+}
+
+const LEET: [(char, char); 6] = [('a', '4'), ('e', '3'), ('i', '1'), ('o', '0'), ('s', '5'), ('t', '7')];
+
+fn leet(candidate: &str) -> String {
+    candidate
+        .chars()
+        .map(|c| LEET.iter().find(|(from, _)| *from == c).map_or(c, |(_, to)| *to))
+        .collect()
+}
+
+fn initial(part: &str) -> String {
+    part.chars().next().map(String::from).unwrap_or_default()
+}
+
+/// Parses numeric suffixes given as a comma separated list of numbers and
+/// inclusive ranges, e.g. `1,7,90-99`.
+///
+/// No more than `limit` suffixes are kept, since every suffix makes at least
+/// one candidate: a huge range such as `0-4294967295` is cut short rather
+/// than generated in full.
+pub fn parse_suffixes(spec: &str, limit: usize) -> Result<Vec<String>, String> {
+    let mut suffixes = Vec::new();
+    for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        match item.split_once('-') {
+            Some((start, end)) => {
+                let width = start.len();
+                let start: u32 = start.parse().map_err(|_| format!("Invalid suffix range \"{}\"", item))?;
+                let end: u32 = end.parse().map_err(|_| format!("Invalid suffix range \"{}\"", item))?;
+                if start > end {
+                    return Err(format!("Invalid suffix range \"{}\"", item));
+                }
+                let room = limit.saturating_sub(suffixes.len());
+                suffixes.extend((start..=end).take(room).map(|n| format!("{:0width$}", n, width = width)));
+            }
+            None => {
+                item.parse::<u32>().map_err(|_| format!("Invalid suffix \"{}\"", item))?;
+                if suffixes.len() < limit {
+                    suffixes.push(item.to_string());
+                }
+            }
+        }
+    }
+    Ok(suffixes)
+}
+
+/// Candidate usernames for a name.
+///
+/// A name made of several words (`John Doe`, `john.doe`) is treated as a
+/// first and last name. The name itself, joined without separator, always
+/// comes first.
+pub fn permute(name: &str, rules: &Rules) -> Vec<String> {
+    let parts: Vec<String> = name
+        .split(|c: char| c.is_whitespace() || matches!(c, '.' | '_' | '-'))
+        .filter(|part| !part.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if parts.is_empty() {
+        return Vec::new();
+    }
+
+    let mut separators = vec![String::new()];
+    for separator in &rules.separators {
+        if !separators.contains(separator) {
+            separators.push(separator.clone());
+        }
+    }
+
+    let mut bases = Vec::new();
+    if let [first, .., last] = parts.as_slice() {
+        let full = parts.concat();
+        bases.push(full);
+        for separator in &separators {
+            bases.push(parts.join(separator));
+            bases.push(format!("{}{}{}", last, separator, first));
+            if rules.truncate {
+                bases.push(format!("{}{}{}", initial(first), separator, last));
+                bases.push(format!("{}{}{}", first, separator, initial(last)));
+                bases.push(format!("{}{}{}", last, separator, initial(first)));
+            }
+        }
+        bases.push(first.clone());
+        bases.push(last.clone());
+    } else {
+        bases.push(parts[0].clone());
+    }
+
+    let limit = rules.limit.max(1);
+    let mut candidates: Vec<String> = Vec::new();
+    let mut push = |candidate: String| {
+        if candidates.len() < limit && !candidate.is_empty() && !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    };
+    for base in &bases {
+        push(base.clone());
+    }
+    for suffix in &rules.suffixes {
+        for base in &bases {
+            push(format!("{}{}", base, suffix));
+        }
+    }
+    if rules.leet {
+        for base in &bases {
+            push(leet(base));
+        }
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(separators: &[&str]) -> Rules {
+        Rules {
+            separators: separators.iter().map(|s| s.to_string()).collect(),
+            ..Rules::default()
+        }
+    }
+
+    #[test]
+    fn parses_numbers_and_ranges() {
+        assert_eq!(parse_suffixes("1, 7,,90-92", 100), Ok(vec!["1", "7", "90", "91", "92"].into_iter().map(String::from).collect()));
+        assert_eq!(parse_suffixes("08-10", 100).unwrap(), ["08", "09", "10"]);
+        assert_eq!(parse_suffixes("", 100), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn rejects_invalid_suffixes() {
+        assert!(parse_suffixes("x", 100).is_err());
+        assert!(parse_suffixes("9-1", 100).is_err());
+        assert!(parse_suffixes("1-", 100).is_err());
+        assert!(parse_suffixes("-5", 100).is_err());
+        assert!(parse_suffixes("1-4294967296", 100).is_err());
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        assert_eq!(parse_suffixes("0-4294967295", 3).unwrap(), ["0", "1", "2"]);
+        assert_eq!(parse_suffixes("1,2,3-9,10", 4).unwrap(), ["1", "2", "3", "4"]);
+        assert!(parse_suffixes("1,2", 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn single_word_names() {
+        assert_eq!(permute("JohnDoe", &rules(&[])), ["johndoe"]);
+        assert!(permute(" .-_ ", &rules(&["."])).is_empty());
+    }
+
+    #[test]
+    fn first_and_last_name_variants() {
+        let candidates = permute("John Doe", &rules(&["."]));
+        assert_eq!(
+            candidates,
+            ["johndoe", "doejohn", "jdoe", "johnd", "doej", "john.doe", "doe.john", "j.doe", "john.d", "doe.j", "john", "doe"]
+        );
+        let untruncated = permute("john_doe", &Rules { truncate: false, ..rules(&["."]) });
+        assert_eq!(untruncated, ["johndoe", "doejohn", "john.doe", "doe.john", "john", "doe"]);
+    }
+
+    #[test]
+    fn suffixes_leet_and_limit() {
+        let rules = Rules {
+            suffixes: vec!["90".to_string()],
+            leet: true,
+            truncate: false,
+            ..rules(&[])
+        };
+        assert_eq!(
+            permute("Jo Tess", &rules),
+            ["jotess", "tessjo", "jo", "tess", "jotess90", "tessjo90", "jo90", "tess90", "j07355", "7355j0", "j0", "7355"]
+        );
+        assert_eq!(permute("Jo Tess", &Rules { limit: 3, ..rules.clone() }), ["jotess", "tessjo", "jo"]);
+        assert_eq!(permute("Jo Tess", &Rules { limit: 0, ..rules }), ["jotess"]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Notify;
+
+/// Completion tracking for the dork mode queue.
+///
+/// Every job is either pending (queued), in flight (taken by a worker or
+/// waiting to be processed) or resolved (done or abandoned). The run is over
+/// once nothing is left outstanding.
+#[derive(Default)]
+pub struct Progress {
+    outstanding: AtomicUsize,
+    pending: AtomicUsize,
+    in_flight: AtomicUsize,
+    done: AtomicUsize,
+    abandoned: AtomicUsize,
+    notify: Notify,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new job was queued. Must be called before the job is sent.
+    pub fn queued(&self) {
+        self.outstanding.fetch_add(1, Ordering::SeqCst);
+        self.pending.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// A worker took a job from the queue.
+    pub fn started(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.pending.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// An in-flight job was put back on the queue.
+    pub fn requeued(&self) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// An in-flight job was fully processed.
+    pub fn done(&self) {
+        self.done.fetch_add(1, Ordering::SeqCst);
+        self.resolve();
+    }
+
+    /// An in-flight job was given up on.
+    pub fn abandoned(&self) {
+        self.abandoned.fetch_add(1, Ordering::SeqCst);
+        self.resolve();
+    }
+
+    fn resolve(&self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if self.outstanding.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.notify.notify_waiters();
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.outstanding.load(Ordering::SeqCst) == 0
+    }
+
+    /// Waits until every queued job is resolved.
+    pub async fn finished(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_finished() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Pending, in-flight, done and abandoned job counts.
+    pub fn counts(&self) -> (usize, usize, usize, usize) {
+        (
+            self.pending.load(Ordering::SeqCst),
+            self.in_flight.load(Ordering::SeqCst),
+            self.done.load(Ordering::SeqCst),
+            self.abandoned.load(Ordering::SeqCst),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn finishes_once_every_job_is_done_or_abandoned() {
+        let progress = Arc::new(Progress::new());
+        progress.finished().await;
+
+        for _ in 0..3 {
+            progress.queued();
+        }
+        let waiter = tokio::spawn({
+            let progress = Arc::clone(&progress);
+            async move { progress.finished().await }
+        });
+        progress.started();
+        progress.requeued();
+        progress.started();
+        progress.done();
+        progress.started();
+        progress.abandoned();
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+        assert_eq!(progress.counts(), (1, 0, 1, 1));
+
+        progress.started();
+        progress.done();
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        assert_eq!(progress.counts(), (0, 0, 2, 1));
+    }
+}
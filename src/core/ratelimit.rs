@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rand::{Rng, rng};
+
+/// Tokens left in a bucket when it was last used.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets shared by every request of a scan, one per key: a host in
+/// API mode, a proxy and engine pair in dork mode.
+///
+/// A bucket holds up to `burst` tokens and gets one back every interval. The
+/// interval is the default one unless an override matches the host or the
+/// engine name; a zero interval disables limiting.
+pub struct RateLimiter {
+    interval: Duration,
+    burst: u32,
+    /// Longest extra wait, as a percentage of the interval.
+    jitter: u8,
+    overrides: Vec<(String, Duration)>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(Duration::ZERO)
+    }
+}
+
+impl RateLimiter {
+    /// One request every `interval` per key.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            burst: 1,
+            jitter: 0,
+            overrides: Vec::new(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Requests sent at once after a pause, before the interval applies.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Adds a random wait of up to `percent` of the interval to every delayed
+    /// request, so requests do not come at a fixed pace.
+    pub fn jitter(mut self, percent: u8) -> Self {
+        self.jitter = percent.min(100);
+        self
+    }
+
+    /// Interval for a host (and its subdomains) or an engine.
+    pub fn limit(mut self, name: &str, interval: Duration) -> Self {
+        self.overrides.push((name.to_lowercase(), interval));
+        self
+    }
+
+    /// Interval applying to `name`, the most specific override first.
+    pub fn interval(&self, name: &str) -> Duration {
+        let name = name.to_lowercase();
+        self.overrides
+            .iter()
+            .filter(|(limit, _)| {
+                name == *limit || name.strip_suffix(limit.as_str()).is_some_and(|rest| rest.ends_with('.'))
+            })
+            .max_by_key(|(limit, _)| limit.len())
+            .map(|(_, interval)| *interval)
+            .unwrap_or(self.interval)
+    }
+
+    /// Waits for a token of the `key` bucket, limited by the interval of
+    /// `name`. The token is taken before waiting, so concurrent callers are
+    /// served in turn.
+    pub async fn wait(&self, key: &str, name: &str) {
+        let delay = self.reserve(key, self.interval(name));
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn reserve(&self, key: &str, interval: Duration) -> Duration {
+        if interval.is_zero() {
+            return Duration::ZERO;
+        }
+        let now = Instant::now();
+        let burst = self.burst as f64;
+        let delay = {
+            let mut buckets = self.buckets.lock().unwrap();
+            let bucket = buckets.entry(key.to_string()).or_insert(Bucket { tokens: burst, updated: now });
+            let refilled = now.duration_since(bucket.updated).as_secs_f64() / interval.as_secs_f64();
+            bucket.tokens = (bucket.tokens + refilled).min(burst) - 1.0;
+            bucket.updated = now;
+            if bucket.tokens >= 0.0 {
+                return Duration::ZERO;
+            }
+            interval.mul_f64(-bucket.tokens)
+        };
+        let jitter = interval.mul_f64(self.jitter as f64 / 100.0);
+        delay + jitter.mul_f64(rng().random::<f64>())
+    }
+}
+
+/// Parses a `NAME=MILLISECONDS` interval override.
+pub fn parse_limit(value: &str) -> Result<(String, u64), String> {
+    let (name, ms) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=MILLISECONDS, got \"{}\"", value))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("missing host or engine in \"{}\"", value));
+    }
+    let ms = ms
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid delay in \"{}\": {}", value, e))?;
+    Ok((name.to_string(), ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn zero_interval_never_waits() {
+        let limiter = RateLimiter::default();
+        for _ in 0..10 {
+            assert_eq!(limiter.reserve("a.example", Duration::ZERO), Duration::ZERO);
+        }
+        assert!(limiter.buckets.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn burst_is_served_at_once_then_paced() {
+        let limiter = RateLimiter::new(SECOND).burst(3);
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve("a.example", SECOND), Duration::ZERO);
+        }
+        let delay = limiter.reserve("a.example", SECOND);
+        assert!(delay > Duration::from_millis(900) && delay <= SECOND, "{:?}", delay);
+        assert_eq!(limiter.reserve("b.example", SECOND), Duration::ZERO);
+    }
+
+    #[test]
+    fn concurrent_callers_queue_up_in_token_debt() {
+        let limiter = RateLimiter::new(SECOND);
+        assert_eq!(limiter.reserve("a.example", SECOND), Duration::ZERO);
+        let delays: Vec<Duration> = (0..3).map(|_| limiter.reserve("a.example", SECOND)).collect();
+        for (turn, delay) in delays.iter().enumerate() {
+            let expected = SECOND * (turn as u32 + 1);
+            assert!(*delay <= expected && *delay > expected - Duration::from_millis(100), "{:?}", delays);
+        }
+    }
+
+    #[test]
+    fn threads_sharing_a_bucket_each_get_their_own_turn() {
+        let limiter = RateLimiter::new(SECOND);
+        let mut delays: Vec<Duration> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|_| scope.spawn(|| limiter.reserve("a.example", SECOND))).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        delays.sort();
+        assert_eq!(delays[0], Duration::ZERO);
+        for (turn, delay) in delays.iter().enumerate().skip(1) {
+            let expected = SECOND * turn as u32;
+            assert!(*delay <= expected && *delay > expected - Duration::from_millis(100), "{:?}", delays);
+        }
+    }
+
+    #[test]
+    fn jitter_only_adds_to_delayed_requests() {
+        let limiter = RateLimiter::new(SECOND).jitter(50);
+        assert_eq!(limiter.reserve("a.example", SECOND), Duration::ZERO);
+        let delay = limiter.reserve("a.example", SECOND);
+        assert!(delay > Duration::from_millis(900) && delay <= Duration::from_millis(1500), "{:?}", delay);
+    }
+
+    #[test]
+    fn overrides_match_hosts_and_subdomains() {
+        let limiter = RateLimiter::new(SECOND)
+            .limit("Example.com", SECOND * 2)
+            .limit("api.example.com", SECOND * 3)
+            .limit("google", SECOND * 4);
+        assert_eq!(limiter.interval("example.com"), SECOND * 2);
+        assert_eq!(limiter.interval("www.EXAMPLE.com"), SECOND * 2);
+        assert_eq!(limiter.interval("api.example.com"), SECOND * 3);
+        assert_eq!(limiter.interval("v2.api.example.com"), SECOND * 3);
+        assert_eq!(limiter.interval("google"), SECOND * 4);
+        assert_eq!(limiter.interval("badexample.com"), SECOND);
+        assert_eq!(limiter.interval("example.com.evil"), SECOND);
+    }
+
+    #[test]
+    fn parses_limits() {
+        assert_eq!(parse_limit("github.com=2000"), Ok(("github.com".to_string(), 2000)));
+        assert_eq!(parse_limit(" google = 500 "), Ok(("google".to_string(), 500)));
+        assert!(parse_limit("github.com").is_err());
+        assert!(parse_limit("=100").is_err());
+        assert!(parse_limit("github.com=fast").is_err());
+        assert!(parse_limit("github.com=-1").is_err());
+    }
+}
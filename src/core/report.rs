@@ -0,0 +1,389 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, create_dir_all};
+use std::io::Error;
+use std::path::Path;
+
+use chrono::DateTime;
+use clap::ValueEnum;
+
+use crate::core::finding::{Finding, Mode};
+use crate::core::history::Run;
+use crate::core::logger::{Logger, time_format};
+use crate::core::request::Verdict;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Html,
+}
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2rem auto; max-width: 70rem; padding: 0 1rem; color: #1f2328; }
+h1 { margin-bottom: .25rem; }
+h2 { border-bottom: 1px solid #d0d7de; padding-bottom: .25rem; margin-top: 2rem; }
+h2 small, h1 small { color: #656d76; font-weight: normal; font-size: .8em; }
+dl.meta { display: grid; grid-template-columns: max-content 1fr; gap: .25rem 1rem; }
+dl.meta dt { font-weight: 600; }
+dl.meta dd { margin: 0; }
+.counts span { display: inline-block; margin-right: .5rem; padding: .1rem .6rem; border-radius: 1rem; font-size: .9em; }
+.found { background: #dafbe1; color: #116329; }
+.not-found { background: #ffebe9; color: #82071e; }
+.uncertain { background: #fff8c5; color: #7d4e00; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: .35rem .5rem; border-bottom: 1px solid #eaeef2; vertical-align: top; }
+td.verdict span { padding: .1rem .5rem; border-radius: 1rem; font-size: .85em; white-space: nowrap; }
+td.detail, .url { color: #656d76; font-size: .9em; word-break: break-all; }
+ol.results li { margin-bottom: .9rem; }
+ol.results p { margin: .2rem 0 0; }
+a { color: #0969da; }
+";
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A link to a scanned URL. Only http(s) URLs are clickable since search
+/// results may link anywhere.
+fn link(url: &str, text: &str) -> String {
+    let lower = url.to_ascii_lowercase();
+    if !(lower.starts_with("http://") || lower.starts_with("https://")) {
+        return escape(text);
+    }
+    format!("<a href=\"{}\" target=\"_blank\" rel=\"noopener noreferrer\">{}</a>", escape(url), escape(text))
+}
+
+fn verdict_class(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::Found => "found",
+        Verdict::NotFound => "not-found",
+        Verdict::Uncertain => "uncertain",
+    }
+}
+
+fn counts(verdicts: impl Iterator<Item = Verdict>) -> String {
+    let mut totals = [0usize; 3];
+    for verdict in verdicts {
+        totals[verdict as usize] += 1;
+    }
+    [Verdict::Found, Verdict::NotFound, Verdict::Uncertain]
+        .iter()
+        .map(|verdict| format!("<span class=\"{}\">{} {}</span>", verdict_class(*verdict), totals[*verdict as usize], verdict))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// A site checked in API mode, as listed in the report.
+struct Profile<'a> {
+    site: &'a str,
+    category: Option<&'a str>,
+    url: &'a str,
+    status: Option<u16>,
+    verdict: Verdict,
+    detail: &'a str,
+}
+
+/// Every site checked by the run, with the verdict recorded for its response.
+fn profiles(run: &Run) -> Vec<Profile<'_>> {
+    let reasons: HashMap<&str, &str> = run
+        .findings
+        .iter()
+        .filter_map(|finding| finding.reason.as_deref().map(|reason| (finding.url.as_str(), reason)))
+        .collect();
+    run.responses
+        .iter()
+        .map(|response| {
+            let detail = match response.status {
+                None => response.outcome.as_str(),
+                Some(_) => reasons.get(response.url.as_str()).copied().unwrap_or_default(),
+            };
+            Profile {
+                site: &response.source,
+                category: response.category.as_deref(),
+                url: &response.url,
+                status: response.status,
+                verdict: response.verdict.unwrap_or(Verdict::Uncertain),
+                detail,
+            }
+        })
+        .collect()
+}
+
+fn metadata(run: &Run) -> String {
+    let duration = match (DateTime::parse_from_rfc3339(&run.started), DateTime::parse_from_rfc3339(&run.finished)) {
+        (Ok(started), Ok(finished)) => Some((finished - started).num_seconds().max(0) as u64),
+        _ => None,
+    };
+    let requests = run.responses.len();
+    let rate = match duration {
+        Some(seconds) if requests > 0 => format!("{:.2} requests/min", requests as f64 * 60.0 / seconds.max(1) as f64),
+        _ => "n/a".to_string(),
+    };
+
+    let mut proxies: BTreeMap<&str, usize> = BTreeMap::new();
+    for proxy in run.responses.iter().filter_map(|response| response.proxy.as_deref()) {
+        *proxies.entry(proxy).or_default() += 1;
+    }
+    let proxies = if proxies.is_empty() {
+        "none".to_string()
+    } else {
+        format!(
+            "<details><summary>{}</summary><ul>{}</ul></details>",
+            proxies.len(),
+            proxies
+                .iter()
+                .map(|(proxy, requests)| format!("<li>{} ({} request(s))</li>", escape(proxy), requests))
+                .collect::<String>()
+        )
+    };
+
+    let rows = [
+        ("Target", escape(&run.target)),
+        ("Run", escape(&run.id)),
+        ("Mode", run.mode.to_string()),
+        ("Started", escape(&run.started)),
+        ("Finished", escape(&run.finished)),
+        ("Duration", duration.map(time_format).unwrap_or_else(|| "n/a".to_string())),
+        ("Requests", requests.to_string()),
+        ("Request rate", rate),
+        ("Proxies used", proxies),
+    ];
+    let mut html = String::from("<dl class=\"meta\">");
+    for (name, value) in rows {
+        html.push_str(&format!("<dt>{}</dt><dd>{}</dd>", name, value));
+    }
+    html.push_str("</dl>");
+    html
+}
+
+fn api_sections(run: &Run) -> String {
+    let profiles = profiles(run);
+    let mut html = format!(
+        "<p class=\"counts\">{}</p>",
+        counts(profiles.iter().map(|profile| profile.verdict))
+    );
+
+    let mut categories: BTreeMap<(bool, &str), Vec<&Profile>> = BTreeMap::new();
+    for profile in &profiles {
+        let key = match profile.category {
+            Some(category) => (false, category),
+            None => (true, "Uncategorized"),
+        };
+        categories.entry(key).or_default().push(profile);
+    }
+
+    for ((_, category), mut profiles) in categories {
+        profiles.sort_by_key(|profile| (profile.verdict as usize, profile.site));
+        html.push_str(&format!(
+            "<h2>{} <small>{} site(s)</small></h2><p class=\"counts\">{}</p>",
+            escape(category),
+            profiles.len(),
+            counts(profiles.iter().map(|profile| profile.verdict))
+        ));
+        html.push_str("<table><tr><th>Site</th><th>Result</th><th>Status</th><th>Profile</th><th>Detail</th></tr>");
+        for profile in profiles {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"verdict\"><span class=\"{}\">{}</span></td><td>{}</td><td>{}</td><td class=\"detail\">{}</td></tr>",
+                escape(profile.site),
+                verdict_class(profile.verdict),
+                profile.verdict,
+                profile.status.map(|status| status.to_string()).unwrap_or_else(|| "-".to_string()),
+                link(profile.url, profile.url),
+                escape(profile.detail)
+            ));
+        }
+        html.push_str("</table>");
+    }
+    html
+}
+
+fn dork_sections(run: &Run) -> String {
+    let mut outcomes: BTreeMap<&str, usize> = BTreeMap::new();
+    for response in &run.responses {
+        *outcomes.entry(response.outcome.as_str()).or_default() += 1;
+    }
+    let mut html = format!("<p>{} result(s)", run.findings.len());
+    if !outcomes.is_empty() {
+        html.push_str(&format!(
+            " from {} response(s): {}",
+            run.responses.len(),
+            outcomes
+                .iter()
+                .map(|(outcome, count)| format!("{} {}", count, escape(outcome)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    html.push_str("</p>");
+
+    let mut queries: Vec<(&str, Vec<&Finding>)> = Vec::new();
+    for finding in &run.findings {
+        let query = finding.query.as_deref().unwrap_or_default();
+        match queries.iter_mut().find(|(q, _)| *q == query) {
+            Some((_, findings)) => findings.push(finding),
+            None => queries.push((query, vec![finding])),
+        }
+    }
+
+    for (query, findings) in queries {
+        html.push_str(&format!(
+            "<h2>{} <small>{} result(s)</small></h2><ol class=\"results\">",
+            escape(query),
+            findings.len()
+        ));
+        for finding in findings {
+            html.push_str(&format!(
+                "<li>{}<div class=\"url\">{} · {}</div>",
+                link(&finding.url, finding.title.as_deref().unwrap_or(&finding.url)),
+                escape(&finding.url),
+                escape(&finding.source)
+            ));
+            if let Some(snippet) = &finding.snippet {
+                html.push_str(&format!("<p>{}</p>", escape(snippet)));
+            }
+            html.push_str("</li>");
+        }
+        html.push_str("</ol>");
+    }
+    html
+}
+
+/// A self-contained HTML page describing a run.
+pub fn render_html(run: &Run) -> String {
+    let body = match run.mode {
+        Mode::Api => api_sections(run),
+        Mode::Dork => dork_sections(run),
+    };
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>Enola report: {target} ({id})</title>\n<style>{style}</style>\n</head>\n<body>\n\
+         <h1>Enola report <small>{target}</small></h1>\n{metadata}\n{body}\n</body>\n</html>\n",
+        target = escape(&run.target),
+        id = escape(&run.id),
+        style = STYLE,
+        metadata = metadata(run),
+        body = body,
+    )
+}
+
+pub fn write_report(logger: &Logger, run: &Run, format: ReportFormat, path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        create_dir_all(parent)?;
+    }
+    logger.inf(&format!("Writing → {}", path.display()), true);
+    match format {
+        ReportFormat::Html => fs::write(path, render_html(run)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::finding::ResponseMeta;
+    use std::time::Duration;
+
+    fn response(site: &str, category: Option<&str>, status: Option<u16>, verdict: Verdict) -> ResponseMeta {
+        let url = format!("https://{}.example/foo", site);
+        let outcome = if status.is_some() { verdict.to_string() } else { "connection refused".to_string() };
+        let mut meta = ResponseMeta::new("foo", site, &url, status, outcome, Duration::from_millis(80));
+        meta.category = category.map(str::to_string);
+        meta.verdict = Some(verdict);
+        meta
+    }
+
+    fn finding(query: &str, url: &str, title: &str) -> Finding {
+        Finding {
+            target: "foo".to_string(),
+            mode: Mode::Dork,
+            source: "google".to_string(),
+            category: None,
+            query: Some(query.to_string()),
+            url: url.to_string(),
+            title: Some(title.to_string()),
+            snippet: Some("<b>bold</b> & more".to_string()),
+            status: Some(200),
+            verdict: None,
+            reason: None,
+            timestamp: "2025-01-31T23:59:59Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(escape("<a href=\"x\">'&'</a>"), "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;");
+    }
+
+    #[test]
+    fn links_only_to_http_urls() {
+        assert_eq!(
+            link("https://a.example/?q=<x>", "a"),
+            "<a href=\"https://a.example/?q=&lt;x&gt;\" target=\"_blank\" rel=\"noopener noreferrer\">a</a>"
+        );
+        assert!(link("HTTP://a.example/", "a").starts_with("<a href="));
+        assert_eq!(link("javascript:alert(1)", "<click>"), "&lt;click&gt;");
+        assert_eq!(link(" javascript:alert(1)", "x"), "x");
+        assert_eq!(link("data:text/html,<script>", "x"), "x");
+    }
+
+    #[test]
+    fn groups_profiles_by_category_with_their_recorded_verdicts() {
+        let mut run = Run::new("20250131T235959.000000Z", "<foo>", Mode::Api);
+        let responses = vec![
+            response("gitlab", Some("coding"), Some(404), Verdict::NotFound),
+            response("github", Some("coding"), Some(200), Verdict::Found),
+            response("forum", None, None, Verdict::Uncertain),
+        ];
+        let mut found = finding("", "https://github.example/foo", "github");
+        found.reason = Some("status=200 <ok>".to_string());
+        run.finish(vec![found], responses);
+
+        let html = render_html(&run);
+        assert!(html.contains("<title>Enola report: &lt;foo&gt; (20250131T235959.000000Z)</title>"));
+        assert!(!html.contains("<foo>"));
+        let coding = html.find("<h2>coding <small>2 site(s)</small></h2>").unwrap();
+        let uncategorized = html.find("<h2>Uncategorized <small>1 site(s)</small></h2>").unwrap();
+        assert!(coding < uncategorized);
+        assert!(html[..coding].contains("<span class=\"found\">1 found</span><span class=\"not-found\">1 not found</span><span class=\"uncertain\">1 uncertain</span>"));
+
+        let github = html.find("<td>github</td><td class=\"verdict\"><span class=\"found\">found</span></td><td>200</td>").unwrap();
+        let gitlab = html.find("<td>gitlab</td><td class=\"verdict\"><span class=\"not-found\">not found</span></td><td>404</td>").unwrap();
+        assert!(coding < github && github < gitlab && gitlab < uncategorized);
+        assert!(html.contains("<td class=\"detail\">status=200 &lt;ok&gt;</td>"));
+        assert!(html[uncategorized..].contains("<td>-</td>"));
+        assert!(html[uncategorized..].contains("<td class=\"detail\">connection refused</td>"));
+    }
+
+    #[test]
+    fn groups_search_results_by_query() {
+        let mut run = Run::new("20250131T235959.000000Z", "foo", Mode::Dork);
+        run.finish(
+            vec![
+                finding("intitle:\"foo\"", "https://a.example/1", "One"),
+                finding("inurl:foo", "javascript:alert(1)", "Two <i>"),
+                finding("intitle:\"foo\"", "https://a.example/3", "Three"),
+            ],
+            Vec::new(),
+        );
+
+        let html = render_html(&run);
+        let first = html.find("<h2>intitle:&quot;foo&quot; <small>2 result(s)</small></h2>").unwrap();
+        let second = html.find("<h2>inurl:foo <small>1 result(s)</small></h2>").unwrap();
+        let three = html.find(">Three</a>").unwrap();
+        assert!(first < three && three < second);
+        assert!(html.contains("<li>Two &lt;i&gt;<div class=\"url\">javascript:alert(1) · google</div>"));
+        assert!(!html.contains("href=\"javascript:"));
+        assert!(html.contains("<p>&lt;b&gt;bold&lt;/b&gt; &amp; more</p>"));
+    }
+}
@@ -1,41 +1,14 @@
-use reqwest::{Client, Method, RequestBuilder, Request, Response, Result};
-use scraper::{Html, Selector};
+use reqwest::{Client, Method, RequestBuilder, Request, Response, Result, StatusCode};
 use urlencoding::encode;
 use rand::{rng, seq::IndexedRandom};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
-pub fn parse(text: &str) -> Vec<(String, String, String)> {
-    let mut results: Vec<(String, String, String)> = Vec::new();
-    let document = Html::parse_document(text);
-    let result_selector = Selector::parse("div.ezO2md").unwrap();
-    let link_selector = Selector::parse("a[href]").unwrap();
-    let title_selector = Selector::parse("span.CVA68e").unwrap();
-    let description_selector = Selector::parse("span.FrIlee").unwrap();
-
-    for result in document.select(&result_selector) {
-        let link_tag = result.select(&link_selector).next();
-        let title_tag = link_tag.and_then(|lt| lt.select(&title_selector).next());
-        let description_tag = result.select(&description_selector).next();
-        
-        if let (Some(link_el), Some(title_el), Some(description_el)) = (link_tag.clone(), title_tag, description_tag) {
-            let href = link_el.value().attr("href").unwrap_or("");
-            let link = href
-                .strip_prefix("/url?q=")
-                .unwrap_or(href)
-                .split('&')
-                .next()
-                .unwrap_or("")
-                .to_string();
-
-            let title = title_el.text().collect::<Vec<_>>().join(" ").trim().to_string();
-            let description = description_el.text().collect::<Vec<_>>().join(" ").trim().to_string();
-
-            results.push((title, link, description));
-        }
-    }
+use crate::core::sites::Site;
 
-    results
-    
-}
+/// Used when no user-agent list is given.
+const DEFAULT_USER_AGENT: &str = concat!("enola/", env!("CARGO_PKG_VERSION"));
 
 pub struct RandomUserAgent {
     user_agents: Vec<String>,
@@ -44,12 +17,14 @@ pub struct RandomUserAgent {
 impl RandomUserAgent {
     pub fn new(user_agents: Vec<String>) -> Self {
         Self {
-            user_agents: user_agents
+            user_agents
         }
     }
 
     pub fn get_random(&self) -> String {
-        self.user_agents.choose(&mut rng()).unwrap().to_string()
+        self.user_agents
+            .choose(&mut rng())
+            .map_or_else(|| DEFAULT_USER_AGENT.to_string(), |user_agent| user_agent.to_string())
     }
 }
 
@@ -64,27 +39,309 @@ impl ApiMode {
         }
     }
 
-    pub fn build(&self, query: &str, client: &Client, user_agent: String) -> Request {
-        let parts: Vec<&str> = query.split(' ').collect();
-        if parts.len() > 3 {
-            panic!("Invalid API mode query format. Expected: <METHOD> <SITE> Option<DATA>");
+    /// The target escaped as the content of a JSON string, so it can be put
+    /// in a body template like `{"user":"USER"}`.
+    fn json_target(&self) -> String {
+        let quoted = serde_json::Value::String(self.target.clone()).to_string();
+        quoted[1..quoted.len() - 1].to_string()
+    }
+
+    pub fn build(&self, site: &Site, client: &Client, user_agent: String) -> std::result::Result<Request, String> {
+        let url = site.url.replace("USER", &encode(self.target.as_str()));
+        let method = Method::from_bytes(site.method.as_bytes())
+            .map_err(|_| format!("Invalid HTTP method \"{}\"", site.method))?;
+        let mut build: RequestBuilder = client.request(method, url)
+            .header("User-Agent", user_agent);
+        for (name, value) in &site.headers {
+            if value.contains("USER") && self.target.contains(['\r', '\n']) {
+                return Err(format!("Target cannot be put in the {} header of {}", name, site.name));
+            }
+            build = build.header(name.as_str(), value.replace("USER", &self.target));
         }
-        let site = parts[0];
-        let method = parts[1];
-        let user_agent = user_agent;
-        let site = site.replace("USER", &encode(self.target.as_str()));
-        let method = Method::from_bytes(method.as_bytes()).expect("Invalid HTTP method");
-        let build: RequestBuilder = client.request(method, &format!("https://{}/", site))
-        .header("User-Agent", user_agent);
-    if parts.len() == 3 {
-        let data = parts[2];
-        return build.body(data.to_string()).build().expect("Failed to build request with body");
+        if let Some(body) = &site.body {
+            build = build.body(body.replace("USER", &self.json_target()));
+        }
+
+        build.build().map_err(|e| format!("Failed to build request for {}: {}", site.name, e))
+    }
+
+    /// Builds the detector declared by a site's rules, with `USER` replaced by
+    /// the target in every rule value, escaped in `match` and `nomatch`
+    /// patterns.
+    pub fn detector(&self, site: &Site) -> std::result::Result<Detector, String> {
+        let rules = site
+            .detect
+            .iter()
+            .map(|rule| {
+                let pattern = matches!(rule.split_once('=').map(|(key, _)| key.trim()), Some("match" | "nomatch"));
+                let target = if pattern { regex::escape(&self.target) } else { self.target.clone() };
+                Rule::parse(&rule.replace("USER", &target))
+            })
+            .collect::<std::result::Result<Vec<Rule>, String>>()?;
+        Ok(Detector::new(rules))
+    }
+}
+
+/// A single condition a response must satisfy for a profile to exist.
+#[derive(Debug, Clone)]
+pub enum Rule {
+    /// The response status must be one of these codes (any 2xx when empty).
+    Status(Vec<u16>),
+    /// The body must contain this text.
+    Contains(String),
+    /// The body must not contain this text.
+    Absent(String),
+    /// The body must match this pattern.
+    Matches(Regex),
+    /// The body must not match this pattern.
+    NotMatches(Regex),
+    /// Being redirected to a URL containing this text means the profile is missing.
+    Redirect(String),
+}
+
+impl Rule {
+    /// Parses a `key=value` rule such as `status=200,301` or `absent=Page not found`.
+    pub fn parse(rule: &str) -> std::result::Result<Self, String> {
+        let (key, value) = rule
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid rule \"{}\". Expected: <KEY>=<VALUE>", rule))?;
+        let value = value.trim();
+        let regex = |v: &str| Regex::new(v).map_err(|e| format!("Invalid pattern in rule \"{}\": {}", rule, e));
+
+        match key.trim() {
+            "status" => value
+                .split(',')
+                .map(|code| code.trim().parse::<u16>().map_err(|_| format!("Invalid status code in rule \"{}\"", rule)))
+                .collect::<std::result::Result<Vec<u16>, String>>()
+                .map(Rule::Status),
+            "contains" => Ok(Rule::Contains(value.to_string())),
+            "absent" => Ok(Rule::Absent(value.to_string())),
+            "match" => regex(value).map(Rule::Matches),
+            "nomatch" => regex(value).map(Rule::NotMatches),
+            "redirect" => Ok(Rule::Redirect(value.to_string())),
+            other => Err(format!("Unknown rule \"{}\"", other)),
         }
+    }
 
-        build.build().expect("Failed to build request")
+    fn needs_body(&self) -> bool {
+        matches!(self, Rule::Contains(_) | Rule::Absent(_) | Rule::Matches(_) | Rule::NotMatches(_))
+    }
+
+    fn check(&self, status: StatusCode, url: &str, body: &str) -> bool {
+        match self {
+            Rule::Status(codes) if codes.is_empty() => status.is_success(),
+            Rule::Status(codes) => codes.contains(&status.as_u16()),
+            Rule::Contains(text) => body.contains(text.as_str()),
+            Rule::Absent(text) => !body.contains(text.as_str()),
+            Rule::Matches(re) => re.is_match(body),
+            Rule::NotMatches(re) => !re.is_match(body),
+            Rule::Redirect(text) => !url.contains(text.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Status(codes) if codes.is_empty() => write!(f, "status=2xx"),
+            Rule::Status(codes) => write!(
+                f,
+                "status={}",
+                codes.iter().map(u16::to_string).collect::<Vec<_>>().join(",")
+            ),
+            Rule::Contains(text) => write!(f, "contains={}", text),
+            Rule::Absent(text) => write!(f, "absent={}", text),
+            Rule::Matches(re) => write!(f, "match={}", re),
+            Rule::NotMatches(re) => write!(f, "nomatch={}", re),
+            Rule::Redirect(text) => write!(f, "redirect={}", text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Found,
+    NotFound,
+    Uncertain,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verdict::Found => "found",
+            Verdict::NotFound => "not found",
+            Verdict::Uncertain => "uncertain",
+        })
+    }
+}
+
+/// The verdict for a profile and the rule that decided it.
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub verdict: Verdict,
+    pub rule: String,
+}
+
+impl Detection {
+    pub fn new(verdict: Verdict, rule: impl Into<String>) -> Self {
+        Self {
+            verdict,
+            rule: rule.into(),
+        }
+    }
+}
+
+/// Decides whether a profile exists from a site's detection rules.
+///
+/// Every rule must hold for a profile to be found; the first one that fails
+/// marks it as not found. Rate-limited and server-error responses, as well as
+/// unreadable bodies, are reported as uncertain since they say nothing about
+/// the profile itself.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    rules: Vec<Rule>,
+}
+
+impl Detector {
+    /// Creates a detector, falling back to "any 2xx status" when no rules are given.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        if rules.is_empty() {
+            return Self {
+                rules: vec![Rule::Status(Vec::new())],
+            };
+        }
+        Self { rules }
+    }
+
+    pub async fn evaluate(&self, response: Response) -> Detection {
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            return Detection::new(Verdict::Uncertain, format!("status {}", status.as_u16()));
+        }
+
+        let url = response.url().to_string();
+        let body = if self.rules.iter().any(Rule::needs_body) {
+            match response.text().await {
+                Ok(body) => body,
+                Err(e) => return Detection::new(Verdict::Uncertain, format!("unreadable body: {}", e)),
+            }
+        } else {
+            String::new()
+        };
+
+        self.decide(status, &url, &body)
+    }
+
+    pub fn decide(&self, status: StatusCode, url: &str, body: &str) -> Detection {
+        if let Some(rule) = self.rules.iter().find(|rule| !rule.check(status, url, body)) {
+            return Detection::new(Verdict::NotFound, rule.to_string());
+        }
+
+        Detection::new(
+            Verdict::Found,
+            self.rules.iter().map(Rule::to_string).collect::<Vec<_>>().join(" & "),
+        )
     }
 }
 
 pub async fn exec(client: &Client, request: Request) -> Result<Response> {
     client.execute(request).await
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() {
+        assert!(matches!(Rule::parse("status=200, 301"), Ok(Rule::Status(codes)) if codes == [200, 301]));
+        assert!(matches!(Rule::parse("contains=Followers"), Ok(Rule::Contains(text)) if text == "Followers"));
+        assert!(matches!(Rule::parse("absent= Page not found "), Ok(Rule::Absent(text)) if text == "Page not found"));
+        assert!(matches!(Rule::parse("match=^@\\w+$"), Ok(Rule::Matches(_))));
+        assert!(matches!(Rule::parse("nomatch=x=y"), Ok(Rule::NotMatches(re)) if re.as_str() == "x=y"));
+        assert!(matches!(Rule::parse("redirect=/login"), Ok(Rule::Redirect(text)) if text == "/login"));
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(Rule::parse("status").is_err());
+        assert!(Rule::parse("status=ok").is_err());
+        assert!(Rule::parse("match=(").is_err());
+        assert!(Rule::parse("length=3").is_err());
+    }
+
+    #[test]
+    fn rules_round_trip_through_display() {
+        for rule in ["status=200,404", "contains=a b", "absent=x", "match=\\d+", "nomatch=y", "redirect=/z"] {
+            assert_eq!(Rule::parse(rule).unwrap().to_string(), rule);
+        }
+    }
+
+    #[test]
+    fn defaults_to_any_success_status() {
+        let detector = Detector::new(Vec::new());
+        let found = detector.decide(StatusCode::NO_CONTENT, "https://a.example/u", "");
+        assert_eq!(found.verdict, Verdict::Found);
+        assert_eq!(found.rule, "status=2xx");
+        assert_eq!(detector.decide(StatusCode::NOT_FOUND, "https://a.example/u", "").verdict, Verdict::NotFound);
+    }
+
+    #[test]
+    fn every_rule_must_hold() {
+        let detector = Detector::new(vec![
+            Rule::parse("status=200").unwrap(),
+            Rule::parse("contains=Followers").unwrap(),
+            Rule::parse("absent=Suspended").unwrap(),
+            Rule::parse("redirect=/login").unwrap(),
+        ]);
+        let url = "https://a.example/u";
+
+        let found = detector.decide(StatusCode::OK, url, "12 Followers");
+        assert_eq!(found.verdict, Verdict::Found);
+        assert_eq!(found.rule, "status=200 & contains=Followers & absent=Suspended & redirect=/login");
+
+        let missing = detector.decide(StatusCode::OK, url, "Suspended, 12 Followers");
+        assert_eq!(missing.verdict, Verdict::NotFound);
+        assert_eq!(missing.rule, "absent=Suspended");
+
+        let redirected = detector.decide(StatusCode::OK, "https://a.example/login", "12 Followers");
+        assert_eq!(redirected.rule, "redirect=/login");
+
+        assert_eq!(detector.decide(StatusCode::GONE, url, "12 Followers").rule, "status=200");
+    }
+
+    fn site(line: &str) -> Site {
+        crate::core::sites::parse_legacy(&[line.to_string()]).0.remove(0)
+    }
+
+    #[test]
+    fn escapes_the_target_in_patterns_only() {
+        let site = site("example.com/USER GET | match=h.USER | contains=USER");
+        let detector = ApiMode::new("a(b".to_string()).detector(&site).unwrap();
+        assert_eq!(detector.decide(StatusCode::OK, "https://example.com/a(b", "h.a(b").verdict, Verdict::Found);
+        assert_eq!(detector.decide(StatusCode::OK, "https://example.com/a(b", "h.ab").verdict, Verdict::NotFound);
+
+        let literal = ApiMode::new("a.b".to_string()).detector(&site).unwrap();
+        assert_eq!(literal.decide(StatusCode::OK, "https://example.com/a.b", "h.axb a.b").verdict, Verdict::NotFound);
+    }
+
+    #[test]
+    fn escapes_the_target_in_bodies() {
+        let site = site("example.com/api POST {\"user\":\"USER\"}");
+        let request = ApiMode::new("a\"b\\".to_string()).build(&site, &Client::new(), String::new()).unwrap();
+        let body = request.body().and_then(|body| body.as_bytes()).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(json["user"], "a\"b\\");
+    }
+
+    #[test]
+    fn keeps_line_breaks_out_of_headers() {
+        let mut site = site("example.com/USER GET");
+        site.headers.insert("X-User".to_string(), "USER".to_string());
+        let mode = ApiMode::new("a\r\nX-Injected: 1".to_string());
+        assert!(mode.build(&site, &Client::new(), String::new()).is_err());
+        let request = ApiMode::new("joe".to_string()).build(&site, &Client::new(), String::new()).unwrap();
+        assert_eq!(request.headers()["X-User"], "joe");
+    }
+}
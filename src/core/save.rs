@@ -1,92 +1,361 @@
-use std::fs::{File, create_dir_all};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions, create_dir_all};
 use std::io::{Error, Write};
 use std::path::{Path, PathBuf};
 use std::{env};
 
+use chrono::Utc;
+use clap::ValueEnum;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::core::event::{Event, Subscription};
+use crate::core::finding::Finding;
 use crate::core::logger::Logger;
+use crate::core::report::ReportFormat;
+use crate::core::request::Verdict;
 
 const APP_NAME: &str = "enola";
 
+/// Base directory of everything enola writes: `$XDG_DATA_HOME/enola` (or
+/// the platform data directory), falling back to the current directory.
+fn app_dir(logger: &Logger) -> PathBuf {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(dirs::data_dir);
+
+    if let Some(data_home) = data_home {
+        return data_home.join(APP_NAME);
+    }
+
+    logger.warn("Fallback to the current directory", false);
+    PathBuf::from(".")
+}
+
+/// Base directory of all results: `<app dir>/results`.
 fn results_dir(logger: &Logger) -> PathBuf {
-    let exe = env::current_exe().unwrap_or_else(|_| PathBuf::from("."));
-    let exe_dir = exe.parent().unwrap_or_else(|| Path::new("."));
+    let path = app_dir(logger).join("results");
+    logger.dbg(&format!("Results directory → {}", path.display()), false);
+    path
+}
+
+/// Default location of the SQLite results store: `<app dir>/enola.db`.
+pub fn database_path(logger: &Logger) -> PathBuf {
+    app_dir(logger).join(format!("{}.db", APP_NAME))
+}
+
+/// Identifier of a run, its UTC start time to the microsecond:
+/// `20250131T235959.123456Z`. Runs started in the same second, such as
+/// parallel cron jobs, still get their own files.
+pub fn new_run_id() -> String {
+    Utc::now().format("%Y%m%dT%H%M%S%.6fZ").to_string()
+}
+
+/// Where the results of a run go.
+///
+/// An explicit output file wins; otherwise results are written to
+/// `<output dir>/<target>/<run id>.<ext>`, the output dir defaulting to the
+/// XDG results directory. Runs are also recorded in the SQLite store when a
+/// database is given, and reported next to the results when a report format
+/// is given.
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    pub output_file: Option<String>,
+    pub output_dir: Option<PathBuf>,
+    pub database: Option<PathBuf>,
+    pub report: Option<ReportFormat>,
+    pub run_id: String,
+}
+
+impl OutputOptions {
+    /// Whether every result would go to the same explicit file.
+    pub fn is_single_file(&self) -> bool {
+        self.output_file
+            .as_deref()
+            .is_some_and(|path| !(Path::new(path).is_dir() || path.ends_with('/')))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Txt,
+    Json,
+    Jsonl,
+    Csv,
+}
 
-    if exe_dir.join("src").exists() {
-        logger.dbg("Local mode → ./results", false);
-        return exe_dir.join("results");
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Txt => "txt",
+            Format::Json => "json",
+            Format::Jsonl => "jsonl",
+            Format::Csv => "csv",
+        }
     }
+}
+
+const CSV_HEADER: &str = "target,mode,source,query,url,title,snippet,status,verdict,reason,timestamp,category";
 
-    if let Some(home) = dirs::home_dir() {
-        let path = home.join(format!("/usr/share/{}/results", APP_NAME));
-        logger.dbg(&format!("System mode → {}", path.display()), false);
-        return path;
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
+}
 
-    logger.warn("Fallback to local ./results", false);
-    PathBuf::from("./results")
+fn csv_row(finding: &Finding) -> String {
+    let value = |v: serde_json::Value| match v {
+        serde_json::Value::String(s) => s,
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    let fields = [
+        finding.target.clone(),
+        value(serde_json::to_value(finding.mode).unwrap_or_default()),
+        finding.source.clone(),
+        finding.query.clone().unwrap_or_default(),
+        finding.url.clone(),
+        finding.title.clone().unwrap_or_default(),
+        finding.snippet.clone().unwrap_or_default(),
+        finding.status.map(|s| s.to_string()).unwrap_or_default(),
+        value(serde_json::to_value(finding.verdict).unwrap_or_default()),
+        finding.reason.clone().unwrap_or_default(),
+        finding.timestamp.clone(),
+        finding.category.clone().unwrap_or_default(),
+    ];
+    fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",")
 }
 
+/// Plain text keeps the historical layouts: a `Title:/Link:/Description:`
+/// block for search results and a bare URL for profiles.
+fn txt_entry(finding: &Finding) -> String {
+    match &finding.title {
+        Some(title) => format!(
+            "Title: {}\nLink: {}\nDescription: {}\n",
+            title,
+            finding.url,
+            finding.snippet.as_deref().unwrap_or("")
+        ),
+        None => finding.url.clone(),
+    }
+}
+
+/// Target name usable as a directory name.
 fn filename(target: &str) -> String {
-    if target.trim().is_empty() {
+    let target = target.trim();
+    if target.is_empty() {
         APP_NAME.to_string()
     } else {
-        target.to_string()
+        target.replace(['/', '\\', ':'], "_")
+    }
+}
+
+/// Directory holding one sub-directory per target.
+pub fn runs_dir(logger: &Logger, output_dir: Option<&Path>) -> PathBuf {
+    match output_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => results_dir(logger),
+    }
+}
+
+/// Directory holding every run of a target.
+pub fn target_dir(logger: &Logger, target: &str, output_dir: Option<&Path>) -> PathBuf {
+    runs_dir(logger, output_dir).join(filename(target))
+}
+
+fn resolve_output_path(logger: &Logger, target: &str, options: &OutputOptions, format: Format) -> PathBuf {
+    let name = format!("{}.{}", options.run_id, format.extension());
+    let path = match &options.output_file {
+        Some(path_str) if options.is_single_file() => PathBuf::from(path_str),
+        Some(dir) => PathBuf::from(dir).join(filename(target)).join(name),
+        None => target_dir(logger, target, options.output_dir.as_deref()).join(name),
+    };
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+        && !parent.exists()
+    {
+        create_dir_all(parent).ok();
     }
+
+    path
 }
 
-fn resolve_output_path(logger: &Logger, target: &str, output: Option<&str>) -> PathBuf {
-    match output {
-        None => {
-            results_dir(logger).join(format!("{}.txt", filename(target)))
+/// A closed results file: its path, the number of findings written to it and
+/// every finding received.
+pub type Closed = (PathBuf, usize, Vec<Finding>);
+
+/// The results file of every target, or why it could not be written.
+pub type ClosedFiles = HashMap<String, Result<Closed, Error>>;
+
+/// Results file of one target, as kept by the [`ResultsSink`] writer.
+struct ResultsFile {
+    path: PathBuf,
+    seen: HashSet<String>,
+    findings: Vec<Finding>,
+    written: usize,
+    /// Records not appended to the file yet.
+    buffer: Vec<u8>,
+    error: Option<Error>,
+}
+
+impl ResultsFile {
+    fn create(path: PathBuf, format: Format) -> Result<Self, Error> {
+        let mut file = File::create(&path)?;
+        match format {
+            Format::Json => write!(file, "[")?,
+            Format::Csv => writeln!(file, "{}", CSV_HEADER)?,
+            Format::Txt | Format::Jsonl => {}
         }
-        Some(path_str) => {
-            let path = PathBuf::from(path_str);
+        Ok(Self {
+            path,
+            seen: HashSet::new(),
+            findings: Vec::new(),
+            written: 0,
+            buffer: Vec::new(),
+            error: None,
+        })
+    }
 
-            if path.is_dir() || path_str.ends_with('/') {
-                create_dir_all(&path).ok();
-                return path.join(format!("{}.txt", filename(target)));
+    fn push(&mut self, finding: Finding, format: Format) {
+        if !self.seen.insert(finding.url.clone()) {
+            return;
+        }
+        if self.error.is_none() && finding.verdict.is_none_or(|verdict| verdict == Verdict::Found) {
+            match write_record(&mut self.buffer, &finding, format, self.written) {
+                Ok(()) => self.written += 1,
+                Err(e) => self.error = Some(e),
             }
+        }
+        self.findings.push(finding);
+    }
 
-            if let Some(parent) = path.parent() {
-                if !parent.exists() {
-                    create_dir_all(parent).ok();
-                }
-            }
+    /// Appends the buffered records, keeping the file open only meanwhile.
+    fn flush(&mut self) {
+        if self.buffer.is_empty() || self.error.is_some() {
+            return;
+        }
+        let appended = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(&self.buffer));
+        self.buffer.clear();
+        if let Err(e) = appended {
+            self.error = Some(e);
+        }
+    }
 
-            path
+    fn close(mut self, format: Format) -> Result<Closed, Error> {
+        if format == Format::Json {
+            writeln!(self.buffer, "{}]", if self.written == 0 { "" } else { "\n" })?;
+        }
+        self.flush();
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok((self.path, self.written, self.findings)),
         }
     }
 }
 
-pub fn save_results(
-    logger: &Logger,
-    target: &str,
-    results: &Vec<(String, String, String)>,
-    output_file: Option<&str>
-) -> Result<(), Error> {
+fn write_record(out: &mut Vec<u8>, finding: &Finding, format: Format, written: usize) -> Result<(), Error> {
+    match format {
+        Format::Txt => writeln!(out, "{}", txt_entry(finding)),
+        Format::Json => write!(out, "{}\n  {}", if written > 0 { "," } else { "" }, serde_json::to_string(finding)?),
+        Format::Jsonl => writeln!(out, "{}", serde_json::to_string(finding)?),
+        Format::Csv => writeln!(out, "{}", csv_row(finding)),
+    }
+}
 
-    let file_path = resolve_output_path(logger, target, output_file);
-    let mut file = File::create(&file_path)?;
+/// Appends findings to the results file of their target, one record at a
+/// time.
+///
+/// Every file is created when the sink is opened. A single writer running on
+/// the blocking thread pool, off the runtime's workers, then serves every
+/// target: findings whose URL was already seen for the target are dropped,
+/// and pending records are appended whenever the queue runs dry and when the
+/// sink is closed. Files are only open while records are appended to them, so
+/// a scan of many targets holds at most one file at a time.
+///
+/// Only search results and profiles found are written to the files, but every
+/// finding is kept for the run's history, whatever its verdict.
+pub struct ResultsSink {
+    tx: mpsc::UnboundedSender<Finding>,
+    handle: JoinHandle<ClosedFiles>,
+}
 
-    logger.inf(&format!("Writing → {}", file_path.display()), true);
+impl ResultsSink {
+    pub fn open(
+        logger: &Logger,
+        targets: &[String],
+        format: Format,
+        options: &OutputOptions
+    ) -> Result<Self, Error> {
+        let mut files = HashMap::new();
+        for target in targets {
+            let path = resolve_output_path(logger, target, options, format);
+            files.insert(target.clone(), ResultsFile::create(path.clone(), format)?);
+            logger.inf(&format!("Writing → {}", path.display()), true);
+        }
 
-    for (title, link, description) in results {
-        writeln!(file, "Title: {}\nLink: {}\nDescription: {}\n", title, link, description)?;
+        let (tx, mut rx) = mpsc::unbounded_channel::<Finding>();
+        let handle = tokio::task::spawn_blocking(move || {
+            while let Some(finding) = rx.blocking_recv() {
+                if let Some(file) = files.get_mut(&finding.target) {
+                    file.push(finding, format);
+                }
+                if rx.is_empty() {
+                    files.values_mut().for_each(ResultsFile::flush);
+                }
+            }
+            files.into_iter().map(|(target, file)| (target, file.close(format))).collect()
+        });
+
+        Ok(Self { tx, handle })
     }
 
-    logger.fnd("Saved successfully.", true);
-    Ok(())
+    pub fn send(&self, finding: Finding) {
+        let _ = self.tx.send(finding);
+    }
+
+    /// Writes out every queued finding and returns, for every target, the
+    /// file path, the number of findings written to it and every finding
+    /// received.
+    pub async fn close(self) -> Result<ClosedFiles, Error> {
+        drop(self.tx);
+        self.handle.await.map_err(Error::other)
+    }
+
+    /// Records the findings read from a scan's events. The files are closed
+    /// once the scan stops.
+    pub fn subscribe(self, mut events: Subscription) -> JoinHandle<Result<ClosedFiles, Error>> {
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                match event {
+                    Event::Finding(finding) => self.send(*finding),
+                    Event::Stopped => break,
+                    _ => {}
+                }
+            }
+            self.close().await
+        })
+    }
 }
 
 pub fn save_results_simple(
     logger: &Logger,
-    target: &str,
-    results: &Vec<String>,
-    output_file: Option<&str>
+    file_path: &Path,
+    results: &[String],
 ) -> Result<(), Error> {
 
-    let file_path = resolve_output_path(logger, target, output_file);
-    let mut file = File::create(&file_path)?;
+    if let Some(parent) = file_path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        create_dir_all(parent)?;
+    }
+    let mut file = File::create(file_path)?;
 
     logger.inf(&format!("Writing → {}", file_path.display()), true);
 
@@ -98,13 +367,124 @@ pub fn save_results_simple(
     Ok(())
 }
 
+/// Where queries that exhausted their retries are written, next to the results.
+pub fn dead_letter_path(logger: &Logger, target: &str, options: &OutputOptions) -> PathBuf {
+    resolve_output_path(logger, target, options, Format::Txt).with_extension("dead.txt")
+}
+
+/// Where the HTML report of a run is written, next to the results.
+pub fn report_path(logger: &Logger, target: &str, options: &OutputOptions) -> PathBuf {
+    resolve_output_path(logger, target, options, Format::Txt).with_extension("html")
+}
+
+/// Whether writing the results of this run would overwrite an existing file,
+/// which can only happen with an explicit output file.
 pub fn is_results_exists(
     logger: &Logger,
     target: &str,
-    output_file: Option<&str>
+    format: Format,
+    options: &OutputOptions
 ) -> (bool, PathBuf) {
-    let file_path = resolve_output_path(logger, target, output_file);
+    let file_path = resolve_output_path(logger, target, options, format);
     let exists = file_path.exists();
 
     (exists, file_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::finding::Mode;
+    use crate::core::logger::LogLevel;
+
+    fn finding() -> Finding {
+        Finding {
+            target: "foo".to_string(),
+            mode: Mode::Dork,
+            source: "google".to_string(),
+            category: None,
+            query: Some("intitle:\"foo\", bar".to_string()),
+            url: "https://example.com/foo".to_string(),
+            title: Some("Foo".to_string()),
+            snippet: Some("first line\nsecond line".to_string()),
+            status: Some(200),
+            verdict: Some(Verdict::NotFound),
+            reason: None,
+            timestamp: "2025-01-31T23:59:59Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field("a\rb"), "\"a\rb\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn writes_csv_rows_in_header_order() {
+        assert_eq!(
+            csv_row(&finding()),
+            "foo,dork,google,\"intitle:\"\"foo\"\", bar\",https://example.com/foo,Foo,\
+             \"first line\nsecond line\",200,not_found,,2025-01-31T23:59:59Z,"
+        );
+        let plain = Finding { query: None, snippet: None, ..finding() };
+        assert_eq!(csv_row(&plain).split(',').count(), CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn keeps_the_historical_text_layouts() {
+        assert_eq!(
+            txt_entry(&finding()),
+            "Title: Foo\nLink: https://example.com/foo\nDescription: first line\nsecond line\n"
+        );
+        let profile = Finding { title: None, ..finding() };
+        assert_eq!(txt_entry(&profile), "https://example.com/foo");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sink_writes_every_url_once_and_keeps_every_verdict() {
+        let dir = env::temp_dir().join(format!("enola-sink-{}", std::process::id()));
+        let options = OutputOptions {
+            output_file: None,
+            output_dir: Some(dir.clone()),
+            database: None,
+            report: None,
+            run_id: new_run_id(),
+        };
+        let logger = Logger::with_output(LogLevel::Error, Box::new(std::io::sink()));
+        let targets = ["foo".to_string(), "bar".to_string()];
+        let sink = ResultsSink::open(&logger, &targets, Format::Jsonl, &options).unwrap();
+        let result = Finding { verdict: None, ..finding() };
+        sink.send(result.clone());
+        sink.send(Finding { title: Some("Again".to_string()), ..result.clone() });
+        sink.send(Finding { url: "https://example.com/bar".to_string(), verdict: Some(Verdict::Found), ..finding() });
+        sink.send(Finding { url: "https://example.com/baz".to_string(), ..finding() });
+        sink.send(Finding { target: "bar".to_string(), ..result.clone() });
+        sink.send(Finding { target: "unknown".to_string(), ..result });
+        let mut closed = sink.close().await.unwrap();
+        assert_eq!(closed.len(), 2);
+
+        let (path, written, findings) = closed.remove("foo").unwrap().unwrap();
+        assert_eq!(path, dir.join("foo").join(format!("{}.jsonl", options.run_id)));
+        assert_eq!(written, 2);
+        assert_eq!(findings.len(), 3);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        let (path, written, _) = closed.remove("bar").unwrap().unwrap();
+        assert_eq!(written, 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn run_ids_sort_by_start_time_below_the_second() {
+        let first = new_run_id();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let second = new_run_id();
+        assert_eq!(first.len(), "20250131T235959.123456Z".len());
+        assert!(first < second);
+    }
+}
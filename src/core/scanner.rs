@@ -0,0 +1,677 @@
+use std::collections::HashMap;
+use std::io::{self, Error};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::Stream;
+use futures::stream::{self, StreamExt};
+use reqwest::{Client, Request};
+use tokio::sync::{Mutex, Notify, Semaphore, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::core::event::{Event, EventBus, Subscription, Summary, TargetSummary};
+use crate::core::finding::{Finding, Mode, ResponseMeta};
+use crate::core::history::{Run, save_run};
+use crate::core::logger::{LogLevel, Logger};
+use crate::core::progress::Progress;
+use crate::core::proxy::{ProxyEntry, ProxyPool, RetryPolicy, Rotation, worker};
+use crate::core::ratelimit::RateLimiter;
+use crate::core::report::write_report;
+use crate::core::request::{ApiMode, Detection, Detector, RandomUserAgent, Verdict, exec};
+use crate::core::save::{Closed, ClosedFiles, Format, OutputOptions, ResultsSink, dead_letter_path, report_path, save_results_simple};
+use crate::core::search::{Engine, Job, Pagination, ResponseCounts, ResponseKind, SearchResult};
+use crate::core::sites::Site;
+
+/// Results files of every target, written by one subscriber until the scan
+/// stops.
+type SinkHandle = JoinHandle<Result<ClosedFiles, Error>>;
+
+/// A running scan, streaming its [`Event`]s until [`Event::RunFinished`].
+pub struct Scan {
+    events: Subscription,
+    cancel: Arc<Notify>,
+}
+
+impl Scan {
+    /// Stops the scan. Results found so far are still written and the stream
+    /// still ends with [`Event::RunFinished`].
+    pub fn cancel(&self) {
+        self.cancel.notify_one();
+    }
+}
+
+impl Stream for Scan {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        Pin::new(&mut self.events).poll_next(cx)
+    }
+}
+
+/// Configures and starts scans, either of profile URLs ([`Scanner::scan_profiles`])
+/// or of search engine dorks through proxies ([`Scanner::search`]).
+pub struct Scanner {
+    targets: Vec<String>,
+    sites: Vec<Site>,
+    queries: Vec<(String, String)>,
+    engines: Vec<Engine>,
+    concurrency: usize,
+    workers: usize,
+    proxies: Vec<ProxyEntry>,
+    rotation: Rotation,
+    retry: RetryPolicy,
+    timeout: Duration,
+    limiter: Arc<RateLimiter>,
+    pages: usize,
+    per_page: usize,
+    user_agents: RandomUserAgent,
+    output: Option<(Format, OutputOptions)>,
+    logger: Arc<Logger>,
+    bus: EventBus,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            sites: Vec::new(),
+            queries: Vec::new(),
+            engines: vec![Engine::Google],
+            concurrency: 3,
+            workers: 5,
+            proxies: Vec::new(),
+            rotation: Rotation::Failure,
+            retry: RetryPolicy {
+                max_retries: 4,
+                backoff: Duration::from_millis(1000),
+            },
+            timeout: Duration::from_secs(15),
+            limiter: Arc::new(RateLimiter::default()),
+            pages: 1,
+            per_page: 10,
+            user_agents: RandomUserAgent::new(Vec::new()),
+            output: None,
+            logger: Arc::new(Logger::with_output(LogLevel::Info, Box::new(io::sink()))),
+            bus: EventBus::new(),
+        }
+    }
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn targets<I, S>(mut self, targets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.targets = targets.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sites checked in API mode.
+    pub fn sites(mut self, sites: Vec<Site>) -> Self {
+        self.sites = sites;
+        self
+    }
+
+    /// Dorks searched in dork mode, as `(target, query)`.
+    pub fn queries(mut self, queries: Vec<(String, String)>) -> Self {
+        self.queries = queries;
+        self
+    }
+
+    pub fn engines(mut self, engines: Vec<Engine>) -> Self {
+        self.engines = engines;
+        self
+    }
+
+    /// Requests sent at once.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Dork mode workers.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Proxy pool of dork mode.
+    pub fn proxies(mut self, proxies: Vec<ProxyEntry>) -> Self {
+        self.proxies = proxies;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Time after which a request is given up, connecting included.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Paces requests per host in API mode and per proxy and engine in dork
+    /// mode; unlimited by default.
+    pub fn rate_limit(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Arc::new(limiter);
+        self
+    }
+
+    /// Result pages fetched per query.
+    pub fn pages(mut self, pages: usize) -> Self {
+        self.pages = pages;
+        self
+    }
+
+    /// Results requested per page.
+    pub fn per_page(mut self, per_page: usize) -> Self {
+        self.per_page = per_page;
+        self
+    }
+
+    pub fn user_agents(mut self, user_agents: Vec<String>) -> Self {
+        self.user_agents = RandomUserAgent::new(user_agents);
+        self
+    }
+
+    /// Writes results files and records runs. Without an output, findings
+    /// are only streamed.
+    pub fn output(mut self, format: Format, options: OutputOptions) -> Self {
+        self.output = Some((format, options));
+        self
+    }
+
+    /// Logger used for file operations; silent by default.
+    pub fn logger(mut self, logger: Arc<Logger>) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    /// Every event of the scan about to be started, for observers other than
+    /// the returned [`Scan`], e.g. a progress display.
+    pub fn subscribe(&self) -> Subscription {
+        self.bus.subscribe()
+    }
+
+    /// Checks the profile URL of every site for every target.
+    pub fn scan_profiles(self) -> Result<Scan, String> {
+        if self.targets.is_empty() {
+            return Err("No targets".to_string());
+        }
+        if self.sites.is_empty() {
+            return Err("No sites found".to_string());
+        }
+        if self.concurrency == 0 {
+            return Err("Number of simultaneous requests must be at least 1".to_string());
+        }
+        let client = Client::builder()
+            .tcp_keepalive(Some(Duration::from_secs(15)))
+            .tcp_nodelay(true)
+            .tcp_keepalive_interval(Some(Duration::from_secs(15)))
+            .tcp_keepalive_retries(3)
+            .timeout(self.timeout)
+            .connect_timeout(self.timeout)
+            .build()
+            .map_err(|e| format!("Failed to build reqwest client: {}", e))?;
+
+        let events = self.bus.subscribe();
+        let mut builds = Vec::with_capacity(self.sites.len() * self.targets.len());
+        for target in &self.targets {
+            let manager = ApiMode::new(target.clone());
+            let mut skipped = Vec::new();
+            for site in &self.sites {
+                if let Err(reason) = site.check_username(target) {
+                    skipped.push((site.name.clone(), reason));
+                    continue;
+                }
+                let build = manager
+                    .build(site, &client, self.user_agents.get_random())
+                    .and_then(|request| manager.detector(site).map(|detector| (request, detector)));
+                match build {
+                    Ok((request, detector)) => builds.push((target.clone(), site.clone(), request, detector)),
+                    Err(error) => self.bus.emit(Event::SiteInvalid {
+                        target: target.clone(),
+                        site: site.name.clone(),
+                        error,
+                    }),
+                }
+            }
+            if !skipped.is_empty() {
+                self.bus.emit(Event::SitesSkipped { target: target.clone(), sites: skipped });
+            }
+        }
+
+        let sink = self.open_sink()?;
+        let recorder = tokio::spawn(collect(self.bus.subscribe()));
+        let cancel = Arc::new(Notify::new());
+        tokio::spawn(self.run_profiles(client, builds, sink, recorder, Arc::clone(&cancel)));
+        Ok(Scan { events, cancel })
+    }
+
+    /// Searches every query on every engine through the proxy pool.
+    pub fn search(mut self) -> Result<Scan, String> {
+        if self.queries.is_empty() {
+            return Err("No queries".to_string());
+        }
+        if self.engines.is_empty() {
+            return Err("No search engines".to_string());
+        }
+        if self.proxies.is_empty() {
+            return Err("No proxies found".to_string());
+        }
+        if self.workers == 0 {
+            return Err("Number of workers must be at least 1".to_string());
+        }
+        if self.concurrency == 0 {
+            return Err("Number of simultaneous requests must be at least 1".to_string());
+        }
+        for (target, _) in &self.queries {
+            if !self.targets.contains(target) {
+                self.targets.push(target.clone());
+            }
+        }
+
+        let events = self.bus.subscribe();
+        let sink = self.open_sink()?;
+        let recorder = tokio::spawn(collect(self.bus.subscribe()));
+        let cancel = Arc::new(Notify::new());
+        tokio::spawn(self.run_search(sink, recorder, Arc::clone(&cancel)));
+        Ok(Scan { events, cancel })
+    }
+
+    /// Creates the results file of every target, written by a single
+    /// subscriber.
+    fn open_sink(&self) -> Result<Option<SinkHandle>, String> {
+        let Some((format, options)) = &self.output else {
+            return Ok(None);
+        };
+        let sink = ResultsSink::open(&self.logger, &self.targets, *format, options)
+            .map_err(|e| format!("Failed to open results file: {}", e))?;
+        Ok(Some(sink.subscribe(self.bus.subscribe())))
+    }
+
+    /// Waits for the results files to be written.
+    async fn close_sink(&self, sink: Option<SinkHandle>) -> ClosedFiles {
+        let Some(sink) = sink else {
+            return ClosedFiles::new();
+        };
+        match sink.await.map_err(Error::other).and_then(|closed| closed) {
+            Ok(closed) => closed,
+            Err(e) => {
+                self.bus.emit(Event::Error(format!("Failed to save results: {}", e)));
+                ClosedFiles::new()
+            }
+        }
+    }
+
+    fn runs(&self, mode: Mode) -> Vec<Run> {
+        let id = self.output.as_ref().map(|(_, options)| options.run_id.as_str()).unwrap_or_default();
+        self.targets.iter().map(|target| Run::new(id, target, mode)).collect()
+    }
+
+    async fn run_profiles(
+        self,
+        client: Client,
+        builds: Vec<(String, Site, Request, Detector)>,
+        sink: Option<SinkHandle>,
+        recorder: JoinHandle<Recorded>,
+        cancel: Arc<Notify>,
+    ) {
+        let start_time = Instant::now();
+        let runs = self.runs(Mode::Api);
+        let total = builds.len();
+
+        let mut results = stream::iter(builds.into_iter().map(|(target, site, request, detector)| {
+            let client = client.clone();
+            let bus = self.bus.clone();
+            let limiter = Arc::clone(&self.limiter);
+            async move { check(&client, target, site, request, detector, &bus, &limiter).await }
+        }))
+        .buffer_unordered(self.concurrency);
+
+        let mut summaries: HashMap<String, TargetSummary> = HashMap::new();
+        let mut done = 0;
+        loop {
+            let finding = tokio::select! {
+                next = results.next() => match next {
+                    Some(finding) => finding,
+                    None => break,
+                },
+                _ = cancel.notified() => break,
+            };
+            done += 1;
+            let summary = summaries.entry(finding.target.clone()).or_default();
+            match finding.verdict {
+                Some(Verdict::Found) => summary.found += 1,
+                Some(Verdict::NotFound) => summary.not_found += 1,
+                _ => summary.uncertain += 1,
+            }
+            self.bus.emit(Event::Finding(Box::new(finding)));
+            self.bus.emit(Event::Progress { done, remaining: total - done });
+        }
+        drop(results);
+        self.bus.emit(Event::Stopped);
+
+        let (mut responses, _) = recorder.await.unwrap_or_default();
+        let mut closed = self.close_sink(sink).await;
+        for run in runs {
+            let mut summary = summaries.remove(&run.target).unwrap_or_default();
+            summary.target = run.target.clone();
+            let results = closed.remove(&run.target);
+            let responses = responses.remove(&run.target).unwrap_or_default();
+            self.finish_target(run, results, responses, &mut summary);
+            self.bus.emit(Event::TargetFinished(Box::new(summary)));
+        }
+
+        self.bus.emit(Event::RunFinished(Box::new(Summary {
+            duration: start_time.elapsed(),
+            requests: done,
+            done,
+            cancelled: total - done,
+            ..Summary::default()
+        })));
+    }
+
+    async fn run_search(self, sink: Option<SinkHandle>, recorder: JoinHandle<Recorded>, cancel: Arc<Notify>) {
+        let start_time = Instant::now();
+        let runs = self.runs(Mode::Dork);
+        let pool = Arc::new(ProxyPool::new(self.proxies.clone(), self.timeout));
+
+        let (tx, rx) = mpsc::unbounded_channel::<Job>();
+        let (result_tx, mut result_rx) = mpsc::channel::<(Job, Vec<SearchResult>)>(100);
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let rx = Arc::new(Mutex::new(rx));
+        let progress = Arc::new(Progress::new());
+        let counts = Arc::new(ResponseCounts::default());
+
+        let mut workers = Vec::with_capacity(self.workers);
+        for i in 0..self.workers {
+            let worker_tx = tx.clone();
+            let worker_rx = rx.clone();
+            let worker_bus = self.bus.clone();
+            let worker_result_tx = result_tx.clone();
+            let worker_semaphore = semaphore.clone();
+            let user_agent_str = self.user_agents.get_random();
+            let worker_pool = Arc::clone(&pool);
+            let rotation = self.rotation;
+            let retry = self.retry;
+            let worker_limiter = Arc::clone(&self.limiter);
+            let worker_progress = Arc::clone(&progress);
+            let worker_counts = Arc::clone(&counts);
+
+            workers.push(tokio::spawn(async move {
+                worker(
+                    i,
+                    worker_pool,
+                    rotation,
+                    &user_agent_str,
+                    worker_rx,
+                    worker_tx,
+                    worker_bus,
+                    worker_result_tx,
+                    worker_semaphore,
+                    worker_limiter,
+                    worker_progress,
+                    worker_counts,
+                    retry,
+                )
+                .await;
+            }));
+        }
+
+        let pagination = Arc::new(Mutex::new(Pagination::default()));
+        let pagination_for_result = Arc::clone(&pagination);
+        let next_tx = tx.clone();
+        let max_pages = self.pages;
+        let progress_for_result = Arc::clone(&progress);
+        let bus = self.bus.clone();
+        let consumer = tokio::spawn(async move {
+            let mut found: HashMap<String, usize> = HashMap::new();
+            let report = |progress: &Progress| {
+                let (pending, in_flight, done, abandoned) = progress.counts();
+                bus.emit(Event::Progress { done: done + abandoned, remaining: pending + in_flight });
+            };
+            while let Some((job, parsed)) = result_rx.recv().await {
+                let seen = !parsed.is_empty();
+                let parsed = pagination_for_result.lock().await.record(&job, parsed);
+                if parsed.is_empty() {
+                    bus.emit(Event::PageEmpty {
+                        target: job.target.clone(),
+                        engine: job.engine.name(),
+                        query: job.query.clone(),
+                        page: job.page,
+                        seen,
+                    });
+                    progress_for_result.done();
+                    report(&progress_for_result);
+                    continue;
+                }
+                if job.page + 1 < max_pages {
+                    progress_for_result.queued();
+                    let _ = next_tx.send(job.next_page());
+                }
+                for result in parsed {
+                    if result.0.is_empty() || result.1.is_empty() {
+                        continue;
+                    }
+                    *found.entry(job.target.clone()).or_default() += 1;
+                    bus.emit(Event::Finding(Box::new(Finding::search(&job, result))));
+                }
+                progress_for_result.done();
+                report(&progress_for_result);
+            }
+            found
+        });
+
+        let engines: Vec<_> = self.engines.iter().map(|engine| engine.backend()).collect();
+        for (target, query) in &self.queries {
+            for engine in &engines {
+                progress.queued();
+                let _ = tx.send(Job::new(Arc::clone(engine), target, query, 0, self.per_page));
+            }
+        }
+        drop(tx);
+
+        tokio::select! {
+            _ = progress.finished() => {}
+            _ = cancel.notified() => {}
+        }
+
+        for handle in &workers {
+            handle.abort();
+        }
+        for handle in workers {
+            let _ = handle.await;
+        }
+        drop(result_tx);
+        let found = match consumer.await {
+            Ok(found) => found,
+            Err(e) => {
+                self.bus.emit(Event::Error(format!("Result processing failed: {}", e)));
+                HashMap::new()
+            }
+        };
+        self.bus.emit(Event::Stopped);
+
+        let (mut responses, mut dead_letters) = recorder.await.unwrap_or_default();
+        let mut closed = self.close_sink(sink).await;
+        let requests = responses.values().map(Vec::len).sum();
+        let pagination = pagination.lock().await;
+        for run in runs {
+            let target = run.target.clone();
+            let mut summary = TargetSummary {
+                target: target.clone(),
+                found: found.get(&target).copied().unwrap_or_default(),
+                dead_letters: dead_letters.remove(&target).unwrap_or_default(),
+                pages: pagination
+                    .summary(&target)
+                    .into_iter()
+                    .map(|(engine, query, stats)| (engine, query.to_string(), stats.pages, stats.links.len()))
+                    .collect(),
+                ..TargetSummary::default()
+            };
+            let results = closed.remove(&target);
+            self.finish_target(run, results, responses.remove(&target).unwrap_or_default(), &mut summary);
+            if !summary.dead_letters.is_empty()
+                && let Some((_, options)) = &self.output
+            {
+                let path = dead_letter_path(&self.logger, &target, options);
+                let mut queries: Vec<String> = Vec::new();
+                for (_, query, _) in &summary.dead_letters {
+                    if !queries.contains(query) {
+                        queries.push(query.clone());
+                    }
+                }
+                match save_results_simple(&self.logger, &path, &queries) {
+                    Ok(()) => summary.dead_letters_path = Some(path),
+                    Err(e) => self.bus.emit(Event::Error(format!("Failed to save dead letters: {}", e))),
+                }
+            }
+            self.bus.emit(Event::TargetFinished(Box::new(summary)));
+        }
+
+        let (pending, in_flight, done, abandoned) = progress.counts();
+        self.bus.emit(Event::RunFinished(Box::new(Summary {
+            duration: start_time.elapsed(),
+            requests,
+            done,
+            abandoned,
+            cancelled: pending + in_flight,
+            responses: ResponseKind::ALL.iter().map(|kind| (*kind, counts.get(*kind))).collect(),
+            pool: Some(pool.health()),
+        })));
+    }
+
+    /// Records the run of a target from its written results file.
+    fn finish_target(
+        &self,
+        mut run: Run,
+        results: Option<Result<Closed, Error>>,
+        responses: Vec<ResponseMeta>,
+        summary: &mut TargetSummary,
+    ) {
+        let (Some(results), Some((_, output))) = (results, &self.output) else {
+            return;
+        };
+        let findings = match results {
+            Ok((path, written, findings)) => {
+                summary.results = Some((path, written));
+                findings
+            }
+            Err(e) => {
+                self.bus.emit(Event::Error(format!("Failed to save results: {}", e)));
+                return;
+            }
+        };
+
+        run.finish(findings, responses);
+        if let Err(e) = save_run(&self.logger, &run, output.output_dir.as_deref()) {
+            self.bus.emit(Event::Error(format!("Failed to record run: {}", e)));
+        }
+        if let Some(format) = output.report
+            && let Err(e) = write_report(&self.logger, &run, format, &report_path(&self.logger, &run.target, output))
+        {
+            self.bus.emit(Event::Error(format!("Failed to write report: {}", e)));
+        }
+        if let Some(path) = &output.database {
+            match store_run(path, &run) {
+                Ok(()) => self.logger.inf(&format!("Run {} stored in {}", run.id, path.display()), true),
+                Err(e) => self.bus.emit(Event::Error(format!("Failed to store run: {}", e))),
+            }
+        }
+    }
+}
+
+/// Responses and abandoned queries of every target.
+type Recorded = (
+    HashMap<String, Vec<ResponseMeta>>,
+    HashMap<String, Vec<(&'static str, String, usize)>>,
+);
+
+/// Collects what a run records of a scan until it stops.
+async fn collect(mut events: Subscription) -> Recorded {
+    let (mut responses, mut dead_letters): Recorded = Default::default();
+    while let Some(event) = events.recv().await {
+        match event {
+            Event::ResponseReceived(meta) => responses.entry(meta.target.clone()).or_default().push(*meta),
+            Event::QueryAbandoned { target, engine, query, page, .. } => {
+                let abandoned: &mut Vec<_> = dead_letters.entry(target).or_default();
+                if !abandoned.iter().any(|(e, q, _)| *e == engine && *q == query) {
+                    abandoned.push((engine, query, page));
+                }
+            }
+            Event::Stopped => break,
+            _ => {}
+        }
+    }
+    (responses, dead_letters)
+}
+
+/// Sends a profile request and decides whether the profile exists.
+async fn check(
+    client: &Client,
+    target: String,
+    site: Site,
+    request: Request,
+    detector: Detector,
+    bus: &EventBus,
+    limiter: &RateLimiter,
+) -> Finding {
+    let url = request.url().to_string();
+    let host = request.url().host_str().unwrap_or_default().to_string();
+    limiter.wait(&host, &host).await;
+    bus.emit(Event::RequestSent { target: target.clone(), url: url.clone(), proxy: None });
+
+    let sent = Instant::now();
+    let result = exec(client, request).await;
+    let elapsed = sent.elapsed();
+    let (finding, meta) = match result {
+        Ok(res) => {
+            let status = res.status().as_u16();
+            let length = res.content_length();
+            let detection = detector.evaluate(res).await;
+            let mut meta = ResponseMeta::new(&target, &site.name, &url, Some(status), detection.verdict.to_string(), elapsed);
+            meta.category = site.category.clone();
+            meta.verdict = Some(detection.verdict);
+            meta.length = length;
+            (Finding::profile(&target, &site, &url, Some(status), &detection), meta)
+        }
+        Err(e) => {
+            let mut meta = ResponseMeta::new(&target, &site.name, &url, None, e.to_string(), elapsed);
+            meta.category = site.category.clone();
+            let detection = Detection::new(Verdict::Uncertain, format!("request failed: {}", e));
+            meta.verdict = Some(detection.verdict);
+            (Finding::profile(&target, &site, &url, None, &detection), meta)
+        }
+    };
+    bus.emit(Event::ResponseReceived(Box::new(meta)));
+    finding
+}
+
+#[cfg(feature = "sqlite")]
+fn store_run(path: &std::path::Path, run: &Run) -> Result<(), String> {
+    crate::core::store::Store::open(path)?.save_run(run)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn store_run(_path: &std::path::Path, _run: &Run) -> Result<(), String> {
+    Err(NO_SQLITE.to_string())
+}
+
+/// Error given when a database is asked for without SQLite support.
+#[cfg(not(feature = "sqlite"))]
+pub const NO_SQLITE: &str = "enola was built without SQLite support, rebuild it with --features sqlite";
@@ -0,0 +1,549 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, LazyLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use clap::ValueEnum;
+use regex::{Captures, Regex};
+use reqwest::StatusCode;
+use scraper::{ElementRef, Html, Selector};
+use urlencoding::{decode, encode};
+
+/// A parsed search result: title, link and description.
+pub type SearchResult = (String, String, String);
+
+/// A search engine usable in dork mode.
+///
+/// Each engine owns the way a dork is written for it, how its result pages
+/// are requested and how they are parsed.
+pub trait SearchEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Rewrites Google-style dork operators into this engine's syntax.
+    fn translate(&self, dork: &str) -> String {
+        dork.to_string()
+    }
+
+    /// URL of the given result page (starting at 0) for an already translated dork.
+    fn url(&self, dork: &str, page: usize, per_page: usize) -> String;
+
+    /// Extra headers sent with every search request.
+    fn headers(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
+    fn parse(&self, html: &str) -> Vec<SearchResult>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Engine {
+    Google,
+    Bing,
+    #[value(name = "duckduckgo", alias = "ddg")]
+    DuckDuckGo,
+    Yandex,
+    Startpage,
+}
+
+impl Engine {
+    pub fn backend(self) -> Arc<dyn SearchEngine> {
+        match self {
+            Engine::Google => Arc::new(Google),
+            Engine::Bing => Arc::new(Bing),
+            Engine::DuckDuckGo => Arc::new(DuckDuckGo),
+            Engine::Yandex => Arc::new(Yandex),
+            Engine::Startpage => Arc::new(Startpage),
+        }
+    }
+}
+
+/// A dork to fetch from one engine, at one result page.
+#[derive(Clone)]
+pub struct Job {
+    pub engine: Arc<dyn SearchEngine>,
+    /// Target the query was built for.
+    pub target: String,
+    pub query: String,
+    pub page: usize,
+    pub per_page: usize,
+    pub url: String,
+    pub attempts: usize,
+}
+
+impl Job {
+    pub fn new(engine: Arc<dyn SearchEngine>, target: &str, query: &str, page: usize, per_page: usize) -> Self {
+        let per_page = per_page.max(1);
+        let url = engine.url(&engine.translate(query), page, per_page);
+        Self {
+            engine,
+            target: target.to_string(),
+            query: query.to_string(),
+            page,
+            per_page,
+            url,
+            attempts: 0,
+        }
+    }
+
+    /// The same query on the following result page.
+    pub fn next_page(&self) -> Self {
+        Self::new(Arc::clone(&self.engine), &self.target, &self.query, self.page + 1, self.per_page)
+    }
+}
+
+#[derive(Default)]
+pub struct PageStats {
+    pub pages: usize,
+    pub links: HashSet<String>,
+}
+
+/// Tracks the pages fetched and the links seen for every query of every engine.
+#[derive(Default)]
+pub struct Pagination {
+    queries: HashMap<(String, &'static str, String), PageStats>,
+}
+
+impl Pagination {
+    /// Records a fetched page and returns the results whose link was not seen
+    /// on a previous page of the same query.
+    pub fn record(&mut self, job: &Job, results: Vec<SearchResult>) -> Vec<SearchResult> {
+        let stats = self
+            .queries
+            .entry((job.target.clone(), job.engine.name(), job.query.clone()))
+            .or_default();
+        stats.pages += 1;
+        results
+            .into_iter()
+            .filter(|(_, link, _)| stats.links.insert(link.clone()))
+            .collect()
+    }
+
+    /// Engine, query and stats of every query of a target with at least one
+    /// fetched page.
+    pub fn summary(&self, target: &str) -> Vec<(&'static str, &str, &PageStats)> {
+        let mut summary: Vec<_> = self
+            .queries
+            .iter()
+            .filter(|((query_target, _, _), _)| query_target == target)
+            .map(|((_, engine, query), stats)| (*engine, query.as_str(), stats))
+            .collect();
+        summary.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        summary
+    }
+}
+
+/// What a search response turned out to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseKind {
+    Results,
+    Empty,
+    Captcha,
+    Consent,
+    RateLimited,
+    Blocked,
+}
+
+impl ResponseKind {
+    pub const ALL: [ResponseKind; 6] = [
+        ResponseKind::Results,
+        ResponseKind::Empty,
+        ResponseKind::Captcha,
+        ResponseKind::Consent,
+        ResponseKind::RateLimited,
+        ResponseKind::Blocked,
+    ];
+
+    /// Whether the page is an actual result page, with or without results.
+    pub fn is_page(self) -> bool {
+        matches!(self, ResponseKind::Results | ResponseKind::Empty)
+    }
+}
+
+impl fmt::Display for ResponseKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ResponseKind::Results => "results",
+            ResponseKind::Empty => "empty",
+            ResponseKind::Captcha => "captcha",
+            ResponseKind::Consent => "consent",
+            ResponseKind::RateLimited => "rate-limited",
+            ResponseKind::Blocked => "blocked",
+        })
+    }
+}
+
+/// Markers of captcha and "unusual traffic" interstitials.
+const CAPTCHA_MARKERS: [&str; 6] = [
+    "/sorry/",
+    "unusual traffic",
+    "g-recaptcha",
+    "h-captcha",
+    "captcha-delivery",
+    "showcaptcha",
+];
+/// Markers of cookie consent walls.
+const CONSENT_MARKERS: [&str; 3] = ["consent.google.", "consent.yahoo.", "before you continue"];
+
+/// Classifies a search response from its status, final URL, body and the
+/// number of results parsed out of it.
+pub fn classify(status: StatusCode, url: &str, body: &str, results: usize) -> ResponseKind {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return ResponseKind::RateLimited;
+    }
+    if results > 0 && status.is_success() {
+        return ResponseKind::Results;
+    }
+
+    let url = url.to_lowercase();
+    let body = body.to_lowercase();
+    if CAPTCHA_MARKERS.iter().any(|marker| url.contains(marker) || body.contains(marker)) {
+        return ResponseKind::Captcha;
+    }
+    if CONSENT_MARKERS.iter().any(|marker| url.contains(marker) || body.contains(marker)) {
+        return ResponseKind::Consent;
+    }
+    if !status.is_success() {
+        return ResponseKind::Blocked;
+    }
+    ResponseKind::Empty
+}
+
+/// Number of responses of every kind seen during a run.
+#[derive(Default)]
+pub struct ResponseCounts {
+    counts: [AtomicUsize; 6],
+}
+
+impl ResponseCounts {
+    pub fn record(&self, kind: ResponseKind) {
+        self.counts[kind as usize].fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn get(&self, kind: ResponseKind) -> usize {
+        self.counts[kind as usize].load(Ordering::SeqCst)
+    }
+}
+
+/// A dork operator such as `intitle:`, at the start of the dork or after a
+/// space, parenthesis or minus sign.
+static OPERATOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(^|[\s(\-])([a-z]+):").unwrap());
+
+/// Renames dork operators, e.g. `intitle:` to `title:`. An empty replacement
+/// drops the operator and keeps its value as a plain term.
+fn rename_operators(dork: &str, renames: &[(&str, &str)]) -> String {
+    OPERATOR
+        .replace_all(dork, |caps: &Captures| {
+            match renames.iter().find(|(from, _)| *from == &caps[2]) {
+                Some((_, "")) => caps[1].to_string(),
+                Some((_, to)) => format!("{}{}:", &caps[1], to),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+fn text(element: ElementRef) -> String {
+    element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Collects results from every `item` element, reading the link from the first
+/// `link` element and the title and description from their own selectors.
+/// Items without a title or link are skipped.
+fn extract(
+    html: &str,
+    item: &str,
+    link: &str,
+    title: &str,
+    description: &str,
+    clean: fn(&str) -> String,
+) -> Vec<SearchResult> {
+    let document = Html::parse_document(html);
+    let item_selector = Selector::parse(item).unwrap();
+    let link_selector = Selector::parse(link).unwrap();
+    let title_selector = Selector::parse(title).unwrap();
+    let description_selector = Selector::parse(description).unwrap();
+
+    document
+        .select(&item_selector)
+        .filter_map(|result| {
+            let link_el = result.select(&link_selector).next()?;
+            let href = clean(link_el.value().attr("href").unwrap_or(""));
+            let title = result.select(&title_selector).next().map(text).unwrap_or_default();
+            let description = result.select(&description_selector).next().map(text).unwrap_or_default();
+
+            if href.is_empty() || title.is_empty() {
+                return None;
+            }
+            Some((title, href, description))
+        })
+        .collect()
+}
+
+pub struct Google;
+
+impl Google {
+    /// Unwraps `/url?q=<url>&...` redirect links.
+    fn clean(href: &str) -> String {
+        href.strip_prefix("/url?q=").unwrap_or(href).split('&').next().unwrap_or("").to_string()
+    }
+}
+
+impl SearchEngine for Google {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn url(&self, dork: &str, page: usize, per_page: usize) -> String {
+        format!(
+            "https://www.google.com/search?q={}&num={}&start={}&hl=en",
+            encode(dork),
+            per_page,
+            page * per_page
+        )
+    }
+
+    fn headers(&self) -> &'static [(&'static str, &'static str)] {
+        &[("Cookie", "CONSENT=YES+; SOCS=CAESHAgBEhIaAB")]
+    }
+
+    fn parse(&self, html: &str) -> Vec<SearchResult> {
+        extract(html, "div.ezO2md", "a[href]", "a[href] span.CVA68e", "span.FrIlee", Google::clean)
+    }
+}
+
+pub struct Bing;
+
+impl SearchEngine for Bing {
+    fn name(&self) -> &'static str {
+        "bing"
+    }
+
+    fn translate(&self, dork: &str) -> String {
+        rename_operators(dork, &[("intext", "inbody"), ("allintitle", "intitle"), ("allinurl", "inurl")])
+            .replace(" | ", " OR ")
+    }
+
+    fn url(&self, dork: &str, page: usize, per_page: usize) -> String {
+        format!(
+            "https://www.bing.com/search?q={}&count={}&first={}",
+            encode(dork),
+            per_page,
+            page * per_page + 1
+        )
+    }
+
+    fn parse(&self, html: &str) -> Vec<SearchResult> {
+        extract(html, "li.b_algo", "h2 a[href]", "h2", "div.b_caption p, p", str::to_string)
+    }
+}
+
+pub struct DuckDuckGo;
+
+impl DuckDuckGo {
+    /// Unwraps `//duckduckgo.com/l/?uddg=<url>` redirect links.
+    fn clean(href: &str) -> String {
+        href.split_once("uddg=")
+            .and_then(|(_, rest)| decode(rest.split('&').next().unwrap_or("")).ok())
+            .map(|link| link.into_owned())
+            .unwrap_or_else(|| href.to_string())
+    }
+}
+
+impl SearchEngine for DuckDuckGo {
+    fn name(&self) -> &'static str {
+        "duckduckgo"
+    }
+
+    fn translate(&self, dork: &str) -> String {
+        rename_operators(dork, &[("intext", ""), ("allintitle", "intitle"), ("allinurl", "inurl")])
+            .replace(" | ", " OR ")
+    }
+
+    fn url(&self, dork: &str, page: usize, per_page: usize) -> String {
+        format!(
+            "https://html.duckduckgo.com/html/?q={}&s={}&dc={}",
+            encode(dork),
+            page * per_page,
+            page * per_page + 1
+        )
+    }
+
+    fn parse(&self, html: &str) -> Vec<SearchResult> {
+        extract(html, "div.result", "a.result__a[href]", "a.result__a", ".result__snippet", DuckDuckGo::clean)
+    }
+}
+
+pub struct Yandex;
+
+impl SearchEngine for Yandex {
+    fn name(&self) -> &'static str {
+        "yandex"
+    }
+
+    fn translate(&self, dork: &str) -> String {
+        rename_operators(
+            dork,
+            &[
+                ("filetype", "mime"),
+                ("ext", "mime"),
+                ("allintitle", "title"),
+                ("intitle", "title"),
+                ("allinurl", "inurl"),
+                ("intext", ""),
+            ],
+        )
+    }
+
+    fn url(&self, dork: &str, page: usize, per_page: usize) -> String {
+        format!(
+            "https://yandex.com/search/?text={}&p={}&numdoc={}&lr=84",
+            encode(dork),
+            page,
+            per_page
+        )
+    }
+
+    fn parse(&self, html: &str) -> Vec<SearchResult> {
+        extract(
+            html,
+            "li.serp-item",
+            ".OrganicTitle a[href], h2 a[href]",
+            ".OrganicTitle-LinkText, h2",
+            ".OrganicText, .Organic-ContentWrapper",
+            str::to_string,
+        )
+    }
+}
+
+pub struct Startpage;
+
+impl Startpage {
+    /// Results on a Startpage page, which cannot be changed.
+    const PAGE_SIZE: usize = 10;
+}
+
+impl SearchEngine for Startpage {
+    fn name(&self) -> &'static str {
+        "startpage"
+    }
+
+    fn url(&self, dork: &str, page: usize, per_page: usize) -> String {
+        format!(
+            "https://www.startpage.com/sp/search?query={}&page={}&cat=web",
+            encode(dork),
+            page * per_page / Startpage::PAGE_SIZE + 1
+        )
+    }
+
+    fn parse(&self, html: &str) -> Vec<SearchResult> {
+        extract(
+            html,
+            "div.w-gl__result, div.result",
+            "a.w-gl__result-title[href], a.result-title[href], a.result-link[href]",
+            "h2, h3, .wgl-title",
+            ".w-gl__description, p.description",
+            str::to_string,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_operators_per_engine() {
+        let dork = "intitle:\"foo\" -intext:bar (allinurl:baz | site:x.com)";
+        assert_eq!(Google.translate(dork), dork);
+        assert_eq!(Bing.translate(dork), "intitle:\"foo\" -inbody:bar (inurl:baz OR site:x.com)");
+        assert_eq!(DuckDuckGo.translate(dork), "intitle:\"foo\" -bar (inurl:baz OR site:x.com)");
+        assert_eq!(Yandex.translate("allintitle:a intitle:b ext:pdf"), "title:a title:b mime:pdf");
+    }
+
+    #[test]
+    fn renames_only_whole_operators() {
+        let renames = [("intext", "inbody")];
+        assert_eq!(rename_operators("intext:a", &renames), "inbody:a");
+        assert_eq!(rename_operators("allintext:a", &renames), "allintext:a");
+        assert_eq!(rename_operators("\"say intext:a\"", &renames), "\"say inbody:a\"");
+        assert_eq!(rename_operators("https://intext:8080", &renames), "https://intext:8080");
+    }
+
+    fn job(query: &str, page: usize) -> Job {
+        Job::new(Engine::Google.backend(), "foo", query, page, 10)
+    }
+
+    fn result(link: &str) -> SearchResult {
+        ("title".to_string(), link.to_string(), String::new())
+    }
+
+    #[test]
+    fn pages_are_built_from_the_page_number() {
+        let first = job("a", 0);
+        assert!(first.url.ends_with("&num=10&start=0&hl=en"));
+        let next = first.next_page();
+        assert_eq!(next.page, 1);
+        assert!(next.url.ends_with("&num=10&start=10&hl=en"));
+    }
+
+    #[test]
+    fn offsets_every_engine_by_the_results_of_earlier_pages() {
+        assert!(Google.url("a", 2, 20).ends_with("?q=a&num=20&start=40&hl=en"));
+        assert!(Bing.url("a", 2, 20).ends_with("?q=a&count=20&first=41"));
+        assert!(DuckDuckGo.url("a", 2, 20).ends_with("?q=a&s=40&dc=41"));
+        assert!(Yandex.url("a", 2, 20).ends_with("?text=a&p=2&numdoc=20&lr=84"));
+        assert!(Startpage.url("a", 2, 20).ends_with("?query=a&page=5&cat=web"));
+        assert!(Startpage.url("a", 0, 20).ends_with("?query=a&page=1&cat=web"));
+    }
+
+    #[test]
+    fn parses_google_results() {
+        let html = r#"<div class="ezO2md"><a href="/url?q=https://a.example/foo&amp;sa=U"><span class="CVA68e">Foo  on A</span></a>
+            <span class="FrIlee">About foo</span></div>
+            <div class="ezO2md"><a href="/url?q=https://b.example/"><span>No title</span></a></div>"#;
+        assert_eq!(
+            Google.parse(html),
+            [("Foo on A".to_string(), "https://a.example/foo".to_string(), "About foo".to_string())]
+        );
+    }
+
+    #[test]
+    fn drops_links_seen_on_earlier_pages_of_the_same_query() {
+        let mut pagination = Pagination::default();
+        let fresh = pagination.record(&job("a", 0), vec![result("https://1"), result("https://2")]);
+        assert_eq!(fresh.len(), 2);
+        let fresh = pagination.record(&job("a", 1), vec![result("https://2"), result("https://3")]);
+        assert_eq!(fresh, [result("https://3")]);
+        let fresh = pagination.record(&job("b", 0), vec![result("https://2")]);
+        assert_eq!(fresh.len(), 1);
+
+        let summary = pagination.summary("foo");
+        assert_eq!(summary.len(), 2);
+        assert_eq!((summary[0].0, summary[0].1, summary[0].2.pages, summary[0].2.links.len()), ("google", "a", 2, 3));
+        assert_eq!((summary[1].1, summary[1].2.pages), ("b", 1));
+        assert!(pagination.summary("bar").is_empty());
+    }
+
+    #[test]
+    fn classifies_search_responses() {
+        let url = "https://www.google.com/search?q=foo";
+        assert_eq!(classify(StatusCode::OK, url, "<html></html>", 3), ResponseKind::Results);
+        assert_eq!(classify(StatusCode::OK, url, "<html></html>", 0), ResponseKind::Empty);
+        assert_eq!(classify(StatusCode::TOO_MANY_REQUESTS, url, "", 3), ResponseKind::RateLimited);
+        assert_eq!(classify(StatusCode::FORBIDDEN, url, "", 0), ResponseKind::Blocked);
+        assert_eq!(classify(StatusCode::FORBIDDEN, url, "", 3), ResponseKind::Blocked);
+    }
+
+    #[test]
+    fn detects_interstitials_from_url_or_body() {
+        let url = "https://www.google.com/search?q=foo";
+        assert_eq!(
+            classify(StatusCode::OK, "https://www.google.com/sorry/index?continue=x", "", 0),
+            ResponseKind::Captcha
+        );
+        assert_eq!(classify(StatusCode::OK, url, "<div class=\"g-recaptcha\">", 0), ResponseKind::Captcha);
+        assert_eq!(classify(StatusCode::OK, "https://consent.google.com/ml?x", "", 0), ResponseKind::Consent);
+        assert_eq!(classify(StatusCode::OK, url, "Before you continue to Google", 0), ResponseKind::Consent);
+        assert_eq!(classify(StatusCode::OK, url, "recaptcha", 5), ResponseKind::Results);
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::core::query::get_lines;
+
+/// A site checked in API mode.
+///
+/// `USER` is replaced by the target in the URL, headers, body and detection rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Site {
+    #[serde(default)]
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub detect: Vec<String>,
+    #[serde(default)]
+    pub username: UsernameRules,
+    #[serde(skip)]
+    pattern: Option<Regex>,
+}
+
+/// What the site accepts as a username. Targets breaking these rules are not
+/// sent to the site.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsernameRules {
+    /// Regex the whole username must match, e.g. `^[a-z][a-z0-9_]*$`.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub min_length: Option<usize>,
+    #[serde(default)]
+    pub max_length: Option<usize>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// A structured manifest, its sites read one by one so that an invalid site
+/// does not spoil the others.
+#[derive(Deserialize)]
+#[serde(bound = "T: Deserialize<'de>")]
+struct Manifest<T> {
+    #[serde(rename = "site", alias = "sites", default)]
+    sites: Vec<T>,
+}
+
+impl Site {
+    /// Converts a legacy `<SITE> <METHOD> [DATA] [| RULE]...` line.
+    ///
+    /// Besides detection rules, `username=<REGEX>` and `length=<MIN>-<MAX>`
+    /// sections set the username rules.
+    pub fn from_legacy(line: &str) -> Result<Self, String> {
+        let mut sections = line.split(" | ");
+        let request = sections.next().unwrap_or("").trim();
+        let mut detect = Vec::new();
+        let mut username = UsernameRules::default();
+        for section in sections.map(str::trim).filter(|rule| !rule.is_empty()) {
+            if let Some(pattern) = section.strip_prefix("username=") {
+                username.pattern = Some(pattern.trim().to_string());
+            } else if let Some(length) = section.strip_prefix("length=") {
+                let invalid = || format!("Invalid length \"{}\". Expected: <MIN>-<MAX>", length);
+                let (min, max) = length.split_once('-').ok_or_else(invalid)?;
+                let bound = |value: &str| -> Result<Option<usize>, String> {
+                    match value.trim() {
+                        "" => Ok(None),
+                        value => value.parse().map(Some).map_err(|_| invalid()),
+                    }
+                };
+                username.min_length = bound(min)?;
+                username.max_length = bound(max)?;
+            } else {
+                detect.push(section.to_string());
+            }
+        }
+
+        let parts: Vec<&str> = request.split(' ').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(format!(
+                "Invalid site \"{}\". Expected: <SITE> <METHOD> Option<DATA>",
+                line
+            ));
+        }
+
+        Ok(Self {
+            name: String::new(),
+            url: format!("https://{}/", parts[0]),
+            method: parts[1].to_string(),
+            headers: BTreeMap::new(),
+            body: parts.get(2).map(|data| data.to_string()),
+            category: None,
+            tags: Vec::new(),
+            detect,
+            username,
+            pattern: None,
+        })
+    }
+
+    /// Host part of the URL, used when a site has no display name.
+    pub fn host(&self) -> &str {
+        let rest = self.url.split_once("://").map_or(self.url.as_str(), |(_, rest)| rest);
+        rest.split(['/', '?', '#']).next().unwrap_or(rest)
+    }
+
+    /// Whether the username can be used on this site, with the reason when it
+    /// cannot. Besides the site's username rules, a username placed in the
+    /// host name must be a valid DNS label.
+    pub fn check_username(&self, username: &str) -> Result<(), String> {
+        let length = username.chars().count();
+        if let Some(min) = self.username.min_length
+            && length < min
+        {
+            return Err(format!("shorter than {} characters", min));
+        }
+        if let Some(max) = self.username.max_length
+            && length > max
+        {
+            return Err(format!("longer than {} characters", max));
+        }
+        if let Some(pattern) = &self.pattern
+            && !pattern.is_match(username)
+        {
+            return Err(format!("does not match {}", self.username.pattern.as_deref().unwrap_or_default()));
+        }
+        if self.host().contains("USER") {
+            let label_ok = username.len() <= 63
+                && !username.starts_with('-')
+                && !username.ends_with('-')
+                && username.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !label_ok {
+                return Err("not a valid host name".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn normalize(mut self) -> Result<Self, String> {
+        if !self.url.contains("://") {
+            self.url = format!("https://{}", self.url);
+        }
+        self.method = self.method.to_uppercase();
+        Method::from_bytes(self.method.as_bytes())
+            .map_err(|_| format!("Invalid HTTP method \"{}\" for {}", self.method, self.url))?;
+        if self.name.is_empty() {
+            self.name = self.host().to_string();
+        }
+        if let Some(pattern) = &self.username.pattern {
+            let pattern = Regex::new(&format!("^(?:{})$", pattern))
+                .map_err(|e| format!("Invalid username pattern for {}: {}", self.name, e))?;
+            self.pattern = Some(pattern);
+        }
+        if let (Some(min), Some(max)) = (self.username.min_length, self.username.max_length)
+            && min > max
+        {
+            return Err(format!("Invalid username length for {}: {} > {}", self.name, min, max));
+        }
+        Ok(self)
+    }
+}
+
+/// Loads a site manifest, returning the sites with a message for every site
+/// or line that was rejected.
+///
+/// `.toml` and `.json` files are read as structured manifests (a `site` list),
+/// anything else as the legacy one-site-per-line text format. Blank lines and
+/// lines starting with `#` are ignored in the legacy format. An invalid site
+/// or line is skipped rather than failing the whole file.
+pub fn load(path: &str) -> Result<(Vec<Site>, Vec<String>), String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+
+    match extension.as_deref() {
+        Some("toml") => {
+            let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+            let manifest = toml::from_str::<Manifest<toml::Value>>(&text).map_err(|e| e.to_string())?;
+            Ok(parse_manifest(manifest.sites, |site| site.try_into::<Site>().map_err(|e| e.to_string())))
+        }
+        Some("json") => {
+            let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+            let manifest = serde_json::from_str::<Manifest<serde_json::Value>>(&text).map_err(|e| e.to_string())?;
+            Ok(parse_manifest(manifest.sites, |site| serde_json::from_value::<Site>(site).map_err(|e| e.to_string())))
+        }
+        _ => get_lines(path).map(|lines| parse_legacy(&lines)).map_err(|e| e.to_string()),
+    }
+}
+
+/// Reads the sites of a structured manifest, keeping the valid ones and
+/// describing the others.
+fn parse_manifest<T>(entries: Vec<T>, read: impl Fn(T) -> Result<Site, String>) -> (Vec<Site>, Vec<String>) {
+    let mut sites = Vec::new();
+    let mut rejected = Vec::new();
+    for (number, entry) in entries.into_iter().enumerate() {
+        match read(entry).and_then(Site::normalize) {
+            Ok(site) => sites.push(site),
+            Err(e) => rejected.push(format!("site {}: {}", number + 1, e.trim())),
+        }
+    }
+    (sites, rejected)
+}
+
+/// Parses legacy lines, keeping the valid sites and describing the others.
+pub(crate) fn parse_legacy(lines: &[String]) -> (Vec<Site>, Vec<String>) {
+    let mut sites = Vec::new();
+    let mut rejected = Vec::new();
+    for (number, line) in lines.iter().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match Site::from_legacy(line).and_then(Site::normalize) {
+            Ok(site) => sites.push(site),
+            Err(e) => rejected.push(format!("line {}: {} ({})", number + 1, line, e)),
+        }
+    }
+    (sites, rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn site(line: &str) -> Site {
+        Site::from_legacy(line).and_then(Site::normalize).unwrap()
+    }
+
+    #[test]
+    fn converts_legacy_lines() {
+        let site = site("api.example.com/users/USER post {\"q\":\"USER\"} | status=200 | absent=Not found");
+        assert_eq!(site.name, "api.example.com");
+        assert_eq!(site.url, "https://api.example.com/users/USER/");
+        assert_eq!(site.method, "POST");
+        assert_eq!(site.body.as_deref(), Some("{\"q\":\"USER\"}"));
+        assert_eq!(site.detect, ["status=200", "absent=Not found"]);
+    }
+
+    #[test]
+    fn reads_username_sections() {
+        let site = site("example.com/USER GET | username=[a-z]+ | length=3-");
+        assert_eq!(site.username.pattern.as_deref(), Some("[a-z]+"));
+        assert_eq!(site.username.min_length, Some(3));
+        assert_eq!(site.username.max_length, None);
+        assert!(site.detect.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_legacy_lines() {
+        assert!(Site::from_legacy("example.com/USER").is_err());
+        assert!(Site::from_legacy("example.com/USER GET a b").is_err());
+        assert!(Site::from_legacy("example.com/USER GET | length=3").is_err());
+        assert!(Site::from_legacy("example.com/USER GET | length=a-4").is_err());
+        assert!(Site::from_legacy("example.com/USER GET | length=5-4").unwrap().normalize().is_err());
+    }
+
+    #[test]
+    fn skips_invalid_legacy_lines() {
+        let lines = ["# comment", "", "a.example/USER GET", "b.example/USER", "c.example/USER G(T"]
+            .map(String::from);
+        let (sites, rejected) = parse_legacy(&lines);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].name, "a.example");
+        assert_eq!(rejected.len(), 2);
+        assert!(rejected[0].starts_with("line 4: b.example/USER"));
+        assert!(rejected[1].starts_with("line 5: c.example/USER G(T"));
+    }
+
+    #[test]
+    fn checks_username_rules() {
+        let site = site("example.com/USER GET | username=[a-z][a-z0-9_]* | length=3-8");
+        assert_eq!(site.check_username("joe_1"), Ok(()));
+        assert_eq!(site.check_username("jo"), Err("shorter than 3 characters".to_string()));
+        assert_eq!(site.check_username("joe_12345"), Err("longer than 8 characters".to_string()));
+        assert_eq!(site.check_username("1joe"), Err("does not match [a-z][a-z0-9_]*".to_string()));
+    }
+
+    #[test]
+    fn pattern_must_match_the_whole_username() {
+        let site = site("example.com/USER GET | username=[a-z]+");
+        assert!(site.check_username("joe").is_ok());
+        assert!(site.check_username("joe.doe").is_err());
+    }
+
+    #[test]
+    fn username_in_host_must_be_a_dns_label() {
+        let site = site("USER.example.com GET");
+        assert!(site.check_username("joe-doe").is_ok());
+        assert!(site.check_username("joe.doe").is_err());
+        assert!(site.check_username("-joe").is_err());
+        assert!(site.check_username(&"a".repeat(64)).is_err());
+        assert!(self::site("example.com/USER GET").check_username("joe.doe").is_ok());
+    }
+
+    fn load_text(name: &str, text: &str) -> Result<(Vec<Site>, Vec<String>), String> {
+        let path = std::env::temp_dir().join(format!("enola-sites-{}-{}", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        let loaded = load(path.to_str().unwrap());
+        fs::remove_file(path).ok();
+        loaded
+    }
+
+    #[test]
+    fn skips_invalid_sites_in_manifests() {
+        let toml = "[[site]]\nurl = \"a.example/USER\"\n\n[[site]]\nname = \"no url\"\n\n\
+                    [[site]]\nurl = \"c.example/USER\"\nmethod = \"G(T\"\n\n[[site]]\nurl = \"d.example/USER\"\n";
+        let (sites, rejected) = load_text("sites.toml", toml).unwrap();
+        assert_eq!(sites.iter().map(|site| site.name.as_str()).collect::<Vec<_>>(), ["a.example", "d.example"]);
+        assert_eq!(rejected.len(), 2);
+        assert!(rejected[0].starts_with("site 2: missing field `url`"), "{:?}", rejected);
+        assert!(rejected[1].starts_with("site 3: Invalid HTTP method"), "{:?}", rejected);
+
+        let json = r#"{"sites": [{"url": "a.example/USER"}, {"url": 3}, {"url": "b.example/USER", "username": {"pattern": "("}}]}"#;
+        let (sites, rejected) = load_text("sites.json", json).unwrap();
+        assert_eq!(sites.len(), 1);
+        assert_eq!(rejected.len(), 2);
+        assert!(rejected[1].starts_with("site 3: Invalid username pattern"), "{:?}", rejected);
+    }
+
+    #[test]
+    fn malformed_manifests_still_fail() {
+        assert!(load_text("broken.toml", "[[site]\n").is_err());
+        assert!(load_text("broken.json", "{\"sites\": [").is_err());
+    }
+}
@@ -0,0 +1,408 @@
+use std::fs::create_dir_all;
+use std::path::Path;
+
+use rusqlite::types::Value;
+use rusqlite::{Connection, Row, params, params_from_iter};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::core::finding::{Finding, Mode};
+use crate::core::history::Run;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS targets (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    target_id INTEGER NOT NULL REFERENCES targets(id),
+    mode TEXT NOT NULL,
+    started TEXT NOT NULL,
+    finished TEXT NOT NULL,
+    UNIQUE (run_id, target_id)
+);
+CREATE TABLE IF NOT EXISTS queries (
+    id INTEGER PRIMARY KEY,
+    run INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    query TEXT NOT NULL,
+    UNIQUE (run, source, query)
+);
+CREATE TABLE IF NOT EXISTS findings (
+    id INTEGER PRIMARY KEY,
+    run INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    category TEXT,
+    query TEXT,
+    url TEXT NOT NULL,
+    title TEXT,
+    snippet TEXT,
+    status INTEGER,
+    verdict TEXT,
+    reason TEXT,
+    timestamp TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS responses (
+    id INTEGER PRIMARY KEY,
+    run INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    source TEXT NOT NULL,
+    category TEXT,
+    query TEXT,
+    url TEXT NOT NULL,
+    proxy TEXT,
+    status INTEGER,
+    outcome TEXT NOT NULL,
+    length INTEGER,
+    elapsed_ms INTEGER NOT NULL,
+    timestamp TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS findings_url ON findings (url);
+";
+
+/// Name of an enum value as it is serialized, e.g. `not_found`.
+fn label<T: Serialize>(value: &T) -> Option<String> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(label)) => Some(label),
+        _ => None,
+    }
+}
+
+fn from_label<T: DeserializeOwned>(label: String) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(label)).ok()
+}
+
+/// Which findings `Store::findings` returns; unset fields match everything.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub target: Option<String>,
+    pub run: Option<String>,
+    pub source: Option<String>,
+    pub mode: Option<String>,
+    pub verdict: Option<String>,
+    /// Substring of the URL.
+    pub url: Option<String>,
+    pub limit: usize,
+}
+
+/// A recorded run with the size of its results.
+#[derive(Debug)]
+pub struct RunSummary {
+    pub target: String,
+    pub id: String,
+    pub mode: String,
+    pub started: String,
+    pub finished: String,
+    pub findings: usize,
+    pub responses: usize,
+}
+
+/// SQLite store of every target, run, query, finding and response.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let conn = Connection::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .and_then(|_| conn.execute_batch(SCHEMA))
+            .map_err(|e| format!("Failed to initialize {}: {}", path.display(), e))?;
+        Ok(Self { conn })
+    }
+
+    /// Stores a run, replacing any earlier copy of it.
+    pub fn save_run(&mut self, run: &Run) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute("INSERT OR IGNORE INTO targets (name) VALUES (?1)", params![run.target])
+            .map_err(|e| format!("Failed to store target: {}", e))?;
+        let target_id: i64 = tx
+            .query_row("SELECT id FROM targets WHERE name = ?1", params![run.target], |row| row.get(0))
+            .map_err(|e| format!("Failed to store target: {}", e))?;
+        tx.execute(
+            "DELETE FROM runs WHERE run_id = ?1 AND target_id = ?2",
+            params![run.id, target_id],
+        )
+        .map_err(|e| format!("Failed to replace run: {}", e))?;
+        tx.execute(
+            "INSERT INTO runs (run_id, target_id, mode, started, finished) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![run.id, target_id, run.mode.to_string(), run.started, run.finished],
+        )
+        .map_err(|e| format!("Failed to store run: {}", e))?;
+        let run_key = tx.last_insert_rowid();
+
+        {
+            let mut query = tx
+                .prepare("INSERT OR IGNORE INTO queries (run, source, query) VALUES (?1, ?2, ?3)")
+                .map_err(|e| e.to_string())?;
+            let mut finding = tx
+                .prepare(
+                    "INSERT INTO findings (run, source, category, query, url, title, snippet, status, verdict, reason, timestamp)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                )
+                .map_err(|e| e.to_string())?;
+            let mut response = tx
+                .prepare(
+                    "INSERT INTO responses (run, source, category, query, url, proxy, status, outcome, length, elapsed_ms, timestamp)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                )
+                .map_err(|e| e.to_string())?;
+
+            for f in &run.findings {
+                finding
+                    .execute(params![
+                        run_key,
+                        f.source,
+                        f.category,
+                        f.query,
+                        f.url,
+                        f.title,
+                        f.snippet,
+                        f.status,
+                        f.verdict.as_ref().and_then(label),
+                        f.reason,
+                        f.timestamp
+                    ])
+                    .map_err(|e| format!("Failed to store finding: {}", e))?;
+            }
+            for r in &run.responses {
+                if let Some(q) = &r.query {
+                    query
+                        .execute(params![run_key, r.source, q])
+                        .map_err(|e| format!("Failed to store query: {}", e))?;
+                }
+                response
+                    .execute(params![
+                        run_key,
+                        r.source,
+                        r.category,
+                        r.query,
+                        r.url,
+                        r.proxy,
+                        r.status,
+                        r.outcome,
+                        r.length.map(|length| length as i64),
+                        r.elapsed_ms as i64,
+                        r.timestamp
+                    ])
+                    .map_err(|e| format!("Failed to store response: {}", e))?;
+            }
+        }
+
+        tx.commit().map_err(|e| format!("Failed to store run: {}", e))
+    }
+
+    /// Runs recorded for a target (or every target), oldest first.
+    pub fn runs(&self, target: Option<&str>) -> Result<Vec<RunSummary>, String> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT t.name, r.run_id, r.mode, r.started, r.finished,
+                        (SELECT COUNT(*) FROM findings f WHERE f.run = r.id),
+                        (SELECT COUNT(*) FROM responses s WHERE s.run = r.id)
+                 FROM runs r JOIN targets t ON t.id = r.target_id
+                 WHERE ?1 IS NULL OR t.name = ?1
+                 ORDER BY r.run_id, t.name",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![target], |row| {
+                Ok(RunSummary {
+                    target: row.get(0)?,
+                    id: row.get(1)?,
+                    mode: row.get(2)?,
+                    started: row.get(3)?,
+                    finished: row.get(4)?,
+                    findings: row.get::<_, i64>(5)? as usize,
+                    responses: row.get::<_, i64>(6)? as usize,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Findings matching a filter, newest run first, with the id of their run.
+    pub fn findings(&self, filter: &Filter) -> Result<Vec<(String, Finding)>, String> {
+        let mut sql = String::from(
+            "SELECT t.name, r.run_id, r.mode, f.source, f.query, f.url, f.title, f.snippet,
+                    f.status, f.verdict, f.reason, f.timestamp, f.category
+             FROM findings f
+             JOIN runs r ON r.id = f.run
+             JOIN targets t ON t.id = r.target_id
+             WHERE 1 = 1",
+        );
+        let mut values: Vec<Value> = Vec::new();
+        let mut clause = |condition: &str, value: String| {
+            values.push(Value::Text(value));
+            sql.push_str(" AND ");
+            sql.push_str(&condition.replace('?', &format!("?{}", values.len())));
+        };
+        if let Some(target) = &filter.target {
+            clause("t.name = ?", target.clone());
+        }
+        if let Some(run) = &filter.run {
+            clause("r.run_id = ?", run.clone());
+        }
+        if let Some(source) = &filter.source {
+            clause("f.source = ? COLLATE NOCASE", source.clone());
+        }
+        if let Some(mode) = &filter.mode {
+            clause("r.mode = ?", mode.to_lowercase());
+        }
+        if let Some(verdict) = &filter.verdict {
+            clause("f.verdict = ?", verdict.to_lowercase().replace([' ', '-'], "_"));
+        }
+        if let Some(url) = &filter.url {
+            clause("instr(f.url, ?) > 0", url.clone());
+        }
+        sql.push_str(" ORDER BY r.run_id DESC, t.name, f.id");
+        if filter.limit > 0 {
+            sql.push_str(&format!(" LIMIT {}", filter.limit));
+        }
+
+        let mut statement = self.conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params_from_iter(values), |row| Ok((row.get(1)?, finding(row)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+}
+
+fn finding(row: &Row) -> rusqlite::Result<Finding> {
+    let mode: String = row.get(2)?;
+    let verdict: Option<String> = row.get(9)?;
+    Ok(Finding {
+        target: row.get(0)?,
+        mode: from_label(mode).unwrap_or(Mode::Api),
+        source: row.get(3)?,
+        category: row.get(12)?,
+        query: row.get(4)?,
+        url: row.get(5)?,
+        title: row.get(6)?,
+        snippet: row.get(7)?,
+        status: row.get(8)?,
+        verdict: verdict.and_then(from_label),
+        reason: row.get(10)?,
+        timestamp: row.get(11)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::finding::ResponseMeta;
+    use crate::core::request::Verdict;
+    use std::time::Duration;
+
+    fn finding(target: &str, mode: Mode, source: &str, url: &str, verdict: Option<Verdict>) -> Finding {
+        Finding {
+            target: target.to_string(),
+            mode,
+            source: source.to_string(),
+            category: (mode == Mode::Api).then(|| "coding".to_string()),
+            query: (mode == Mode::Dork).then(|| format!("intitle:\"{}\"", target)),
+            url: url.to_string(),
+            title: None,
+            snippet: None,
+            status: Some(200),
+            verdict,
+            reason: verdict.map(|_| "status=200".to_string()),
+            timestamp: "2025-01-31T23:59:59Z".to_string(),
+        }
+    }
+
+    fn run(id: &str, target: &str, mode: Mode, findings: Vec<Finding>) -> Run {
+        let mut run = Run::new(id, target, mode);
+        let responses = findings
+            .iter()
+            .map(|f| {
+                let mut meta = ResponseMeta::new(&f.target, &f.source, &f.url, f.status, "found".to_string(), Duration::from_millis(120));
+                meta.query = f.query.clone();
+                meta
+            })
+            .collect();
+        run.finish(findings, responses);
+        run
+    }
+
+    fn urls(store: &Store, filter: Filter) -> Vec<String> {
+        store.findings(&filter).unwrap().into_iter().map(|(_, f)| f.url).collect()
+    }
+
+    #[test]
+    fn round_trips_runs_and_filters_findings() {
+        let mut store = Store::open(Path::new(":memory:")).unwrap();
+        let first = run(
+            "20250101T000000.000000Z",
+            "foo",
+            Mode::Api,
+            vec![
+                finding("foo", Mode::Api, "GitHub", "https://github.com/foo", Some(Verdict::Found)),
+                finding("foo", Mode::Api, "GitLab", "https://gitlab.com/foo", Some(Verdict::NotFound)),
+            ],
+        );
+        let second = run(
+            "20250102T000000.000000Z",
+            "foo",
+            Mode::Dork,
+            vec![finding("foo", Mode::Dork, "google", "https://a.example/foo", None)],
+        );
+        let third = run(
+            "20250103T000000.000000Z",
+            "bar",
+            Mode::Api,
+            vec![finding("bar", Mode::Api, "GitHub", "https://github.com/bar", Some(Verdict::Found))],
+        );
+        for run in [&first, &second, &third, &first] {
+            store.save_run(run).unwrap();
+        }
+
+        let runs = store.runs(None).unwrap();
+        assert_eq!(
+            runs.iter().map(|r| (r.id.as_str(), r.target.as_str(), r.mode.as_str(), r.findings, r.responses)).collect::<Vec<_>>(),
+            [
+                ("20250101T000000.000000Z", "foo", "api", 2, 2),
+                ("20250102T000000.000000Z", "foo", "dork", 1, 1),
+                ("20250103T000000.000000Z", "bar", "api", 1, 1),
+            ]
+        );
+        assert_eq!(runs[0].started, first.started);
+        assert_eq!(store.runs(Some("bar")).unwrap().len(), 1);
+
+        let stored = store.findings(&Filter::default()).unwrap();
+        assert_eq!(stored.len(), 4);
+        assert_eq!(stored[0].0, "20250103T000000.000000Z");
+        let (id, dork) = &stored[1];
+        assert_eq!(id, "20250102T000000.000000Z");
+        assert_eq!(serde_json::to_value(dork).unwrap(), serde_json::to_value(&second.findings[0]).unwrap());
+        assert_eq!(serde_json::to_value(&stored[2].1).unwrap(), serde_json::to_value(&first.findings[0]).unwrap());
+
+        let filter = |f: fn(&mut Filter)| {
+            let mut filter = Filter::default();
+            f(&mut filter);
+            filter
+        };
+        assert_eq!(urls(&store, filter(|f| f.target = Some("bar".to_string()))), ["https://github.com/bar"]);
+        assert_eq!(urls(&store, filter(|f| f.run = Some("20250102T000000.000000Z".to_string()))), ["https://a.example/foo"]);
+        assert_eq!(
+            urls(&store, filter(|f| f.source = Some("github".to_string()))),
+            ["https://github.com/bar", "https://github.com/foo"]
+        );
+        assert_eq!(urls(&store, filter(|f| f.mode = Some("DORK".to_string()))), ["https://a.example/foo"]);
+        assert_eq!(urls(&store, filter(|f| f.verdict = Some("Not Found".to_string()))), ["https://gitlab.com/foo"]);
+        assert_eq!(urls(&store, filter(|f| f.url = Some("/foo".to_string()))).len(), 3);
+        assert_eq!(urls(&store, filter(|f| f.limit = 2)), ["https://github.com/bar", "https://a.example/foo"]);
+        let both = Filter {
+            target: Some("foo".to_string()),
+            verdict: Some("found".to_string()),
+            ..Filter::default()
+        };
+        assert_eq!(urls(&store, both), ["https://github.com/foo"]);
+    }
+}
@@ -0,0 +1,24 @@
+//! Enola hunts down social media accounts by username, either by checking
+//! profile URLs on a list of sites or by searching dorks on search engines
+//! through proxies.
+//!
+//! [`Scanner`] runs a scan and streams its [`Event`]s; the `enola` binary is
+//! one consumer of it.
+
+/// Internals the `enola` binary is built on. Public only so that the binary
+/// can reach them: hidden from the documentation, not covered by semver and
+/// free to change in any release. Use the re-exports below instead.
+#[doc(hidden)]
+pub mod core;
+
+pub use crate::core::event::{Event, EventBus, Subscription, Summary, TargetSummary};
+pub use crate::core::finding::{Finding, Mode, ResponseMeta};
+pub use crate::core::logger::{LogLevel, Logger};
+pub use crate::core::proxy::{PoolHealth, ProxyEntry, RetryPolicy, Rotation};
+pub use crate::core::ratelimit::RateLimiter;
+pub use crate::core::report::ReportFormat;
+pub use crate::core::request::Verdict;
+pub use crate::core::save::{Format, OutputOptions};
+pub use crate::core::scanner::{Scan, Scanner};
+pub use crate::core::search::{Engine, ResponseKind};
+pub use crate::core::sites::{Site, load as load_sites};
@@ -1,86 +1,178 @@
-mod core;
+use enola::core::config::{self, Config, Source};
+use enola::core::finding::Mode;
+use enola::core::history::{diff, find_runs, list_runs, load_run};
+use enola::core::logger::{LogLevel, Logger, Prompts, time_format};
+use enola::core::proxy::{Anonymity, ProxyEntry, RetryPolicy, Rotation, check_proxy, parse_proxies, real_ips};
+use enola::core::query::{get_lines, Query};
+use enola::core::ratelimit::{RateLimiter, parse_limit};
+use enola::core::report::{ReportFormat, write_report};
+use enola::core::request::Verdict;
+use enola::core::save::{Format, OutputOptions, database_path, is_results_exists, new_run_id, save_results_simple, target_dir};
+#[cfg(not(feature = "sqlite"))]
+use enola::core::scanner::NO_SQLITE;
+use enola::core::search::Engine;
+#[cfg(feature = "sqlite")]
+use enola::core::store::{Filter, Store};
+use enola::core::{permute, sites};
+use enola::{Event, Scan, Scanner, Subscription, Summary, TargetSummary};
 
-use crate::core::logger::{LogLevel, Logger};
-use crate::core::request::{ApiMode, RandomUserAgent, exec, parse};
-use crate::core::query::{get_lines, Query};
-use crate::core::proxy::worker;
-use crate::core::save::{is_results_exists, save_results, save_results_simple};
-
-use clap::Parser;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
 use dirs::home_dir;
-use reqwest::{Client, Request, Response};
-use std::env::current_dir;
+use std::collections::BTreeMap;
+use std::env::{self, current_dir};
+use std::ffi::OsString;
+use std::io::{self, IsTerminal};
 use std::sync::{Arc, LazyLock as Lazy};
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, Mutex, Semaphore};
 use futures::stream::{self, StreamExt};
 use std::path::PathBuf;
 
 
 fn data_default() -> PathBuf {
-    let mut path = home_dir().unwrap_or_else(|| std::env::current_dir().unwrap());
+    let mut path = home_dir().unwrap_or_else(|| current_dir().unwrap());
     path.push(".enola");
     path
 }
 
-fn time_format(seconds: u64) -> String {
-    let hours = seconds / 3600;
-    let minutes = (seconds % 3600) / 60;
-    let secs = seconds % 60;
-
-    let mut parts = Vec::new();
-    if hours > 0 {
-        parts.push(format!("{} hour{}", hours, if hours > 1 { "s" } else { "" }));
-    }
-    if minutes > 0 {
-        parts.push(format!("{} minute{}", minutes, if minutes > 1 { "s" } else { "" }));
-    }
-    if secs > 0 || parts.is_empty() {
-        parts.push(format!("{} second{}", secs, if secs > 1 { "s" } else { "" }));
-    }
-
-    if parts.len() > 1 {
-        let last = parts.len() - 1;
-        let value = std::mem::take(&mut parts[last]);
-        parts[last] = format!("and {}", value);
-    }
-
-    parts.join(", ")
-}
-
-static DEFAULT_UTILS: Lazy<PathBuf> = Lazy::new(|| data_default());
+static DEFAULT_UTILS: Lazy<PathBuf> = Lazy::new(data_default);
 static DEFAULT_UTILS_SITES: Lazy<PathBuf> = Lazy::new(|| DEFAULT_UTILS.join("dorks/sites/all.txt"));
 static DEFAULT_UTILS_PAYLOADS: Lazy<PathBuf> = Lazy::new(|| DEFAULT_UTILS.join("dorks/payloads/general.txt"));
 static DEFAULT_USER_AGENTS: Lazy<PathBuf> = Lazy::new(|| DEFAULT_UTILS.join("request/user_agents.txt"));
 static DEFAULT_API_SITES: Lazy<PathBuf> = Lazy::new(|| DEFAULT_UTILS.join("apis/profile_urls.txt"));
+static DEFAULT_PROXIES: Lazy<PathBuf> = Lazy::new(|| DEFAULT_UTILS.join("request/proxies.txt"));
 
 
 #[derive(Parser)]
 #[command(name = "Enola")]
 #[command(version = "1.0.0")]
 #[command(about = "A powerful search tool", long_about = "Enola uses Google Dorks to get information")]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
-    #[arg(short = 't', long, help = "Target", help_heading = "Target")]
-    target: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(
+        short = 't',
+        long,
+        help = "Target, can be repeated",
+        help_heading = "Target",
+        required_unless_present = "targets_file"
+    )]
+    target: Vec<String>,
+
+    #[arg(long, help = "File with one target per line", help_heading = "Target")]
+    targets_file: Option<String>,
+
+    #[arg(
+        long,
+        help = "Scan username variants of every target (a name like \"John Doe\" is split into first and last name)",
+        help_heading = "Target",
+        default_value_t = false
+    )]
+    permute: bool,
+
+    #[arg(long, help = "Separators put between name parts", help_heading = "Target", default_value = "._-")]
+    separators: String,
+
+    #[arg(long, help = "Add leetspeak variants", help_heading = "Target", default_value_t = false)]
+    leet: bool,
+
+    #[arg(long, help = "Numeric suffixes, e.g. 1,7,90-99", help_heading = "Target")]
+    suffixes: Option<String>,
+
+    #[arg(long, help = "Do not shorten name parts to their initial", help_heading = "Target", default_value_t = false)]
+    no_truncate: bool,
+
+    #[arg(long, help = "Maximum number of variants per target", help_heading = "Target", default_value_t = 100)]
+    max_permutations: usize,
 
     #[arg(
         short = 'v',
         long,
         help = "Verbose Level (1-7)",
         value_parser = clap::value_parser!(u8).range(1..=8),
+        global = true,
         help_heading = "Miscellaneous",
         default_value_t = 5
     )]
     verbose: u8,
 
+    #[arg(
+        short = 'y',
+        long,
+        help = "Answer yes to every confirmation prompt",
+        help_heading = "Miscellaneous",
+        default_value_t = false
+    )]
+    yes: bool,
+
+    #[arg(
+        long,
+        help = "Never prompt, take the default answer instead (automatic when stdin is not a terminal)",
+        help_heading = "Miscellaneous",
+        conflicts_with = "yes",
+        default_value_t = false
+    )]
+    no_input: bool,
+
+    #[arg(
+        long,
+        help = "Configuration file (default: ~/.config/enola/config.toml)",
+        global = true,
+        help_heading = "Miscellaneous"
+    )]
+    config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Configuration profile to apply (or ENOLA_PROFILE)",
+        global = true,
+        help_heading = "Miscellaneous"
+    )]
+    profile: Option<String>,
+
     #[arg(
         short = 'o',
         long,
-        help = "Output path for results",
+        help = "Output file for results",
         help_heading = "Settings",
     )]
     output_path: Option<String>,
 
+    #[arg(
+        long,
+        help = "Directory for results, written to <DIR>/<target>/<run-id>.<ext>",
+        help_heading = "Settings",
+    )]
+    output_dir: Option<PathBuf>,
+
+    #[arg(
+        short = 'f',
+        long,
+        help = "Format of the results file",
+        help_heading = "Settings",
+        value_enum,
+        default_value_t = Format::Txt
+    )]
+    format: Format,
+
+    #[arg(
+        long,
+        help = "Also record the run in a SQLite database (default: <data dir>/enola/enola.db)",
+        help_heading = "Settings",
+        value_name = "PATH",
+        num_args = 0..=1
+    )]
+    sqlite: Option<Option<PathBuf>>,
+
+    #[arg(
+        long,
+        help = "Also write a report of the run next to the results",
+        help_heading = "Settings",
+        value_enum
+    )]
+    report: Option<ReportFormat>,
+
     #[arg(short = 'p', long, help = "Provide your Dork", help_heading = "Settings")]
     payload: Option<String>,
 
@@ -108,7 +200,7 @@ struct Cli {
     #[arg(
         short,
         long = "api-sites",
-        help = "Use APIs search engine's site",
+        help = "Sites manifest for API mode (TOML, JSON or legacy text)",
         help_heading = "Settings",
         default_value_t = DEFAULT_API_SITES.to_string_lossy().into_owned()
     )]
@@ -125,6 +217,15 @@ struct Cli {
     #[arg(long, help = "Proxy List", help_heading = "Request")]
     proxies: Option<String>,
 
+    #[arg(
+        long,
+        help = "When workers switch proxies (Only for Proxy-Mode)",
+        help_heading = "Request",
+        value_enum,
+        default_value_t = Rotation::Failure
+    )]
+    rotate: Rotation,
+
     #[arg(
         short = 'w',
         long = "workers",
@@ -146,12 +247,82 @@ struct Cli {
     #[arg(
         short = 'd',
         long,
-        help = "Delay between requests (in milliseconds)",
+        help = "Delay between requests to a host, or through a proxy to an engine (in milliseconds, 0 to disable)",
         help_heading = "Request",
         default_value_t = 5000
     )]
     delay: u64,
 
+    #[arg(
+        long = "delay-for",
+        value_name = "NAME=MS",
+        value_parser = parse_limit,
+        help = "Delay for a host (and its subdomains) or an engine, overriding --delay (repeatable)",
+        help_heading = "Request"
+    )]
+    delay_for: Vec<(String, u64)>,
+
+    #[arg(
+        long,
+        help = "Requests sent without delay after a pause, per host or per proxy and engine",
+        help_heading = "Request",
+        value_parser = clap::value_parser!(u32).range(1..),
+        default_value_t = 1
+    )]
+    burst: u32,
+
+    #[arg(
+        long,
+        help = "Random extra delay, up to this percentage of the delay",
+        help_heading = "Request",
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        default_value_t = 20
+    )]
+    jitter: u8,
+
+    #[arg(
+        short = 'n',
+        long,
+        help = "Number of results per search page",
+        help_heading = "Request",
+        default_value_t = 10
+    )]
+    results: usize,
+
+    #[arg(
+        long,
+        help = "Maximum number of result pages fetched per query",
+        help_heading = "Request",
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value_t = 1
+    )]
+    pages: u64,
+
+    #[arg(
+        long,
+        help = "Retries per query before it is dead-lettered (Only for Proxy-Mode)",
+        help_heading = "Request",
+        default_value_t = 4
+    )]
+    retries: usize,
+
+    #[arg(
+        long,
+        help = "Initial retry backoff, doubled on every retry (in milliseconds)",
+        help_heading = "Request",
+        default_value_t = 1000
+    )]
+    backoff: u64,
+
+    #[arg(
+        long,
+        help = "Time after which a request is given up, connecting included (in milliseconds)",
+        help_heading = "Request",
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value_t = 15000
+    )]
+    timeout: u64,
+
     #[arg(
         short,
         long = "google-dork-mode",
@@ -161,183 +332,626 @@ struct Cli {
     )]
     google_dork_mode: bool,
 
+    #[arg(
+        short = 'e',
+        long = "engine",
+        help = "Search engine(s) used in dork mode",
+        help_heading = "Mode",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Engine::Google]
+    )]
+    engines: Vec<Engine>,
+
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage proxy lists
+    Proxy {
+        #[command(subcommand)]
+        action: ProxyCommand,
+    },
+    /// Show what changed between two runs of a target
+    Diff(DiffArgs),
+    /// List and filter findings recorded in the SQLite database
+    Query(QueryArgs),
+    /// Write a report of a recorded run
+    Report(ReportArgs),
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective value of every option and where it comes from
+    Show,
+}
+
+#[derive(Subcommand)]
+enum ProxyCommand {
+    /// Test a proxy list and write the working proxies sorted by latency
+    Check(CheckArgs),
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    #[arg(
+        short = 'i',
+        long,
+        help = "Proxy list to check",
+        default_value_t = DEFAULT_PROXIES.to_string_lossy().into_owned()
+    )]
+    input: String,
+
+    #[arg(short = 'o', long, help = "Output path for working proxies (default: <input>.checked.txt)")]
+    output: Option<String>,
+
+    #[arg(
+        short = 'u',
+        long,
+        help = "URL fetched through each proxy, should echo the request headers",
+        default_value = "http://httpbin.org/get"
+    )]
+    url: String,
+
+    #[arg(short = 'c', long, help = "Number of proxies checked at once", default_value_t = 50)]
+    concurrency: usize,
+
+    #[arg(long, help = "Timeout per proxy (in milliseconds)", default_value_t = 10000)]
+    timeout: u64,
+
+    #[arg(
+        long,
+        help = "Minimum anonymity level kept",
+        value_enum,
+        default_value_t = Anonymity::Transparent
+    )]
+    anonymity: Anonymity,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    #[arg(help = "Target whose runs are compared")]
+    target: String,
+
+    #[arg(long, help = "Older run id (default: the run before --to)")]
+    from: Option<String>,
+
+    #[arg(long, help = "Newer run id (default: the latest run)")]
+    to: Option<String>,
+
+    #[arg(short = 'l', long, help = "List the recorded runs instead", default_value_t = false)]
+    list: bool,
+
+    #[arg(long, help = "Directory the runs were written to")]
+    output_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct QueryArgs {
+    #[arg(long, help = "SQLite database (default: <data dir>/enola/enola.db)")]
+    db: Option<PathBuf>,
+
+    #[arg(short = 't', long, help = "Only findings of this target")]
+    target: Option<String>,
+
+    #[arg(short = 'r', long, help = "Only findings of this run id")]
+    run: Option<String>,
+
+    #[arg(short = 's', long, help = "Only findings from this site or search engine")]
+    source: Option<String>,
+
+    #[arg(short = 'm', long, help = "Only findings of this mode (api or dork)")]
+    mode: Option<String>,
+
+    #[arg(long, help = "Only findings with this verdict (found, not_found or uncertain)")]
+    verdict: Option<String>,
+
+    #[arg(short = 'u', long, help = "Only findings whose URL contains this text")]
+    url: Option<String>,
+
+    #[arg(short = 'n', long, help = "Maximum number of findings shown (0 for all)", default_value_t = 100)]
+    limit: usize,
+
+    #[arg(long, help = "List the recorded runs instead", default_value_t = false)]
+    runs: bool,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    #[arg(help = "Run id to report on")]
+    run: String,
+
+    #[arg(short = 't', long, help = "Only the run of this target")]
+    target: Option<String>,
+
+    #[arg(short = 'f', long, help = "Report format", value_enum, default_value_t = ReportFormat::Html)]
+    format: ReportFormat,
+
+    #[arg(short = 'o', long, help = "Output file (default: <target dir>/<run-id>.html)")]
+    output: Option<PathBuf>,
+
+    #[arg(long, help = "Directory the runs were written to")]
+    output_dir: Option<PathBuf>,
+}
+
+/// Targets given with `--target` and `--targets-file`, in order and without
+/// duplicates, or their username variants with `--permute`.
+fn load_targets(args: &Cli) -> Result<Vec<String>, String> {
+    let lines = match &args.targets_file {
+        Some(path) => get_lines(path).map_err(|e| format!("Failed to load targets: {}", e))?,
+        None => Vec::new(),
+    };
+    let mut targets: Vec<String> = Vec::new();
+    for target in args.target.iter().chain(&lines) {
+        let target = target.trim();
+        if target.is_empty() || target.starts_with('#') || targets.iter().any(|t| t == target) {
+            continue;
+        }
+        targets.push(target.to_string());
+    }
+    if targets.is_empty() {
+        return Err("No targets were given".to_string());
+    }
+    if !args.permute {
+        return Ok(targets);
+    }
+
+    let rules = permute::Rules {
+        separators: args.separators.chars().map(String::from).collect(),
+        leet: args.leet,
+        suffixes: match &args.suffixes {
+            Some(spec) => permute::parse_suffixes(spec, args.max_permutations)?,
+            None => Vec::new(),
+        },
+        truncate: !args.no_truncate,
+        limit: args.max_permutations,
+    };
+    let mut candidates: Vec<String> = Vec::new();
+    for target in &targets {
+        for candidate in permute::permute(target, &rules) {
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    Ok(candidates)
+}
+
+/// Loads a proxy list, warning about every line that is not a valid proxy.
+fn load_proxies(path: &str, logger: &Logger) -> Result<Vec<ProxyEntry>, String> {
+    let lines = get_lines(path).map_err(|e| format!("Failed to load proxies: {}", e))?;
+    let (proxies, rejected) = parse_proxies(&lines);
+    for message in &rejected {
+        logger.warn(&format!("Skipping invalid proxy at {}", message), false);
+    }
+    if !rejected.is_empty() {
+        logger.warn(&format!("{} invalid proxy line(s) were skipped", rejected.len()), true);
+    }
+    Ok(proxies)
+}
+
+/// Request pacing from `--delay`, `--burst`, `--jitter` and `--delay-for`.
+fn rate_limiter(args: &Cli) -> RateLimiter {
+    args.delay_for.iter().fold(
+        RateLimiter::new(Duration::from_millis(args.delay))
+            .burst(args.burst)
+            .jitter(args.jitter),
+        |limiter, (name, ms)| limiter.limit(name, Duration::from_millis(*ms)),
+    )
+}
+
+async fn run_proxy_check(args: &CheckArgs, logger: &Arc<Logger>) -> Result<(), String> {
+    if args.concurrency == 0 {
+        return Err("Concurrency must be at least 1".to_string());
+    }
+
+    logger.inf(&format!("loading proxies from {}...", args.input), false);
+    let proxies = load_proxies(&args.input, logger)?;
+    if proxies.is_empty() {
+        logger.err("no proxies were found", true);
+        return Err("No proxies found".to_string());
+    }
+    logger.dbg(&format!("{} proxy(ies) were loaded", proxies.len()), true);
+
+    let timeout = Duration::from_millis(args.timeout);
+    let real = real_ips(&args.url, timeout).await;
+    if real.is_empty() {
+        logger.warn(
+            &format!("{} did not echo our IP address, transparent proxies cannot be detected", args.url),
+            true,
+        );
+    }
+
+    logger.inf(&format!("checking proxies against {}...", args.url), false);
+    let start_time = Instant::now();
+    let total = proxies.len();
+    let mut working: Vec<_> = stream::iter(proxies.iter().map(|proxy| {
+        let real = &real;
+        async move {
+            let check = check_proxy(proxy, &args.url, timeout, real).await;
+            match &check {
+                Some(check) => logger.fnd(
+                    &format!("{} => {} ms, {}", check.proxy, check.latency.as_millis(), check.anonymity),
+                    false,
+                ),
+                None => logger.nfnd(&format!("{} => dead", proxy), false),
+            }
+            check
+        }
+    }))
+    .buffer_unordered(args.concurrency)
+    .filter_map(|check| async move { check })
+    .filter(|check| {
+        let keep = check.anonymity >= args.anonymity;
+        async move { keep }
+    })
+    .collect()
+    .await;
+    working.sort_by_key(|check| check.latency);
+
+    let duration = start_time.elapsed();
+    logger.inf(
+        &format!(
+            "{} of {} proxy(ies) kept, checked in {}",
+            working.len(),
+            total,
+            time_format(duration.as_secs())
+        ),
+        true,
+    );
+
+    let output = args
+        .output
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(&args.input).with_extension("checked.txt"));
+    let lines: Vec<String> = working.into_iter().map(|check| check.proxy.url()).collect();
+    save_results_simple(logger, &output, &lines)
+        .map_err(|e| format!("Failed to save proxies: {}", e))?;
+
+    Ok(())
+}
+
+fn run_diff(args: &DiffArgs, logger: &Logger) -> Result<(), String> {
+    let output_dir = args.output_dir.as_deref();
+    let ids = list_runs(logger, &args.target, output_dir).map_err(|e| format!("Failed to list runs: {}", e))?;
+    if ids.is_empty() {
+        return Err(format!("No runs recorded for {}", args.target));
+    }
+
+    if args.list {
+        for id in &ids {
+            match load_run(logger, &args.target, id, output_dir) {
+                Ok(run) => logger.inf(
+                    &format!("{} => {} mode, {} finding(s), finished {}", run.id, run.mode, run.findings.len(), run.finished),
+                    true,
+                ),
+                Err(e) => logger.warn(&format!("{} => unreadable: {}", id, e), true),
+            }
+        }
+        return Ok(());
+    }
+
+    let to = match &args.to {
+        Some(id) => id.clone(),
+        None => ids.last().cloned().unwrap_or_default(),
+    };
+    let from = match &args.from {
+        Some(id) => id.clone(),
+        None => ids
+            .iter()
+            .rev()
+            .find(|id| **id < to)
+            .cloned()
+            .ok_or_else(|| format!("No run of {} before {} to compare with", args.target, to))?,
+    };
+
+    let old = load_run(logger, &args.target, &from, output_dir).map_err(|e| format!("Failed to load run: {}", e))?;
+    let new = load_run(logger, &args.target, &to, output_dir).map_err(|e| format!("Failed to load run: {}", e))?;
+    if old.mode != new.mode {
+        logger.warn(
+            &format!("Comparing a {} run with a {} run", old.mode, new.mode),
+            true,
+        );
+    }
+
+    logger.inf(&format!("Comparing {} → {} for {}", from, to, args.target), true);
+    let changes = diff(&old, &new);
+    for finding in &changes.added {
+        logger.fnd(&format!("+ {} ({})", finding.url, finding.source), true);
+    }
+    for finding in &changes.removed {
+        logger.nfnd(&format!("- {} ({})", finding.url, finding.source), true);
+    }
+    let show = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+    for (before, after) in &changes.changed {
+        let mut parts = Vec::new();
+        if before.verdict != after.verdict {
+            parts.push(format!(
+                "{} → {}",
+                show(before.verdict.map(|v| v.to_string())),
+                show(after.verdict.map(|v| v.to_string()))
+            ));
+        }
+        if before.status != after.status {
+            parts.push(format!(
+                "status {} → {}",
+                show(before.status.map(|s| s.to_string())),
+                show(after.status.map(|s| s.to_string()))
+            ));
+        }
+        if before.title != after.title {
+            parts.push(format!("title \"{}\" → \"{}\"", show(before.title.clone()), show(after.title.clone())));
+        }
+        logger.warn(&format!("~ {} ({})", after.url, parts.join(", ")), true);
+    }
+    if changes.is_empty() {
+        logger.inf("No changes", true);
+    }
+    logger.inf(
+        &format!(
+            "{} new, {} gone, {} changed, {} unchanged",
+            changes.added.len(),
+            changes.removed.len(),
+            changes.changed.len(),
+            changes.unchanged
+        ),
+        true,
+    );
+    Ok(())
+}
+
+fn run_report(args: &ReportArgs, logger: &Logger) -> Result<(), String> {
+    let output_dir = args.output_dir.as_deref();
+    let runs = match &args.target {
+        Some(target) => vec![load_run(logger, target, &args.run, output_dir).map_err(|e| format!("Failed to load run: {}", e))?],
+        None => find_runs(logger, &args.run, output_dir).map_err(|e| format!("Failed to load run: {}", e))?,
+    };
+    if runs.is_empty() {
+        return Err(format!("No run {} was recorded", args.run));
+    }
+    if runs.len() > 1 && args.output.is_some() {
+        return Err(format!("Run {} has {} targets, pick one with --target", args.run, runs.len()));
+    }
+
+    for run in &runs {
+        let path = match &args.output {
+            Some(path) => path.clone(),
+            None => target_dir(logger, &run.target, output_dir).join(format!("{}.html", run.id)),
+        };
+        write_report(logger, run, args.format, &path).map_err(|e| format!("Failed to write report: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn run_query(args: &QueryArgs, logger: &Logger) -> Result<(), String> {
+    let path = args.db.clone().unwrap_or_else(|| database_path(logger));
+    if !path.exists() {
+        return Err(format!("No database at {}", path.display()));
+    }
+    let store = Store::open(&path)?;
+
+    if args.runs {
+        let runs = store.runs(args.target.as_deref())?;
+        for run in &runs {
+            logger.inf(
+                &format!(
+                    "{} [{}] => {} mode, {} finding(s), {} response(s), {} → {}",
+                    run.target, run.id, run.mode, run.findings, run.responses, run.started, run.finished
+                ),
+                true,
+            );
+        }
+        logger.inf(&format!("{} run(s)", runs.len()), true);
+        return Ok(());
+    }
+
+    let filter = Filter {
+        target: args.target.clone(),
+        run: args.run.clone(),
+        source: args.source.clone(),
+        mode: args.mode.clone(),
+        verdict: args.verdict.clone(),
+        url: args.url.clone(),
+        limit: args.limit,
+    };
+    let findings = store.findings(&filter)?;
+    for (run, finding) in &findings {
+        let detail = match (&finding.title, finding.status) {
+            (Some(title), _) => title.clone(),
+            (None, Some(status)) => status.to_string(),
+            (None, None) => "no response".to_string(),
+        };
+        let verdict = finding.verdict.map(|v| format!(", {}", v)).unwrap_or_default();
+        logger.fnd(
+            &format!("{} [{}] {} {} ({}{})", finding.target, run, finding.source, finding.url, detail, verdict),
+            true,
+        );
+    }
+    logger.inf(&format!("{} finding(s)", findings.len()), true);
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn run_query(_args: &QueryArgs, _logger: &Logger) -> Result<(), String> {
+    Err(NO_SQLITE.to_string())
 }
 
 async fn run_proxy_mode(
     args: &Cli,
-    target: &str,
+    targets: &[String],
+    output: &OutputOptions,
     logger: &Arc<Logger>,
-    user_agent: &RandomUserAgent,
+    user_agents: Vec<String>,
 ) -> Result<(), String> {
-    logger.inf("Google dork mode enabled", true);
+    logger.inf(
+        &format!(
+            "Dork mode enabled ({})",
+            args.engines.iter().map(|engine| engine.backend().name()).collect::<Vec<_>>().join(", ")
+        ),
+        true,
+    );
     logger.inf("loading queries...", false);
-    let query: Vec<String> = if args.queries.is_none() {
-        Query::new(&args.sites, &args.payloads, target)
-            .build()
-            .map_err(|e| format!("Failed to build queries: {}", e))?
-    } else {
-        get_lines(args.queries.as_deref().unwrap()).map_err(|e| format!("Failed to load queries: {}", e))?
+    let lines = match &args.queries {
+        Some(path) => Some(get_lines(path).map_err(|e| format!("Failed to load queries: {}", e))?),
+        None => None,
     };
+    if targets.len() > 1 {
+        let payloads;
+        let (kind, templates) = match &lines {
+            Some(lines) => ("Query", lines),
+            None => {
+                payloads = get_lines(&args.payloads).map_err(|e| format!("Failed to load payloads: {}", e))?;
+                ("Payload", &payloads)
+            }
+        };
+        if let Some(template) = templates
+            .iter()
+            .find(|template| !template.trim().is_empty() && !template.contains("STRING"))
+        {
+            return Err(format!(
+                "{} \"{}\" has no STRING placeholder, so its results cannot be told apart between {} targets",
+                kind,
+                template,
+                targets.len()
+            ));
+        }
+    }
+    let mut queries: Vec<(String, String)> = Vec::new();
+    for target in targets {
+        let built = match &lines {
+            Some(lines) => lines.iter().map(|line| line.replace("STRING", target)).collect(),
+            None => Query::new(&args.sites, &args.payloads, target)
+                .build()
+                .map_err(|e| format!("Failed to build queries: {}", e))?,
+        };
+        queries.extend(built.into_iter().map(|query| (target.clone(), query)));
+    }
+    logger.dbg(&format!("{} query(ies) for {} target(s)", queries.len(), targets.len()), true);
 
     if args.simultaneous_requests > 3 {
         logger.warn(
             "Using more than 3 simultaneous requests with proxies may increase RAM usage",
             true,
         );
-        let input = logger.input("Do you want to continue? [Y/n]").to_lowercase();
-        if !input.starts_with('y') && !input.is_empty() {
+        if !logger.confirm("Do you want to continue?", true) {
             return Err("User interrupted".to_string());
         }
     }
 
     logger.inf("loading proxies...", false);
-    let proxies = get_lines(args.proxies.as_ref().unwrap()).map_err(|e| format!("Failed to load proxies: {}", e))?;
+    let proxies = load_proxies(args.proxies.as_ref().unwrap(), logger)?;
     if proxies.is_empty() {
         logger.err("no proxies were found", true);
         return Err("No proxies found".to_string());
     }
     logger.dbg(&format!("{} proxy(ies) were loaded", proxies.len()), true);
 
-    logger.inf("starting workers...", false);
-    let start_time = Instant::now();
-
-    let (tx, rx) = mpsc::channel::<String>(100);
-    let (log_tx, mut log_rx) = mpsc::channel::<String>(100);
-    let (result_tx, mut result_rx) = mpsc::channel::<(String, Result<Response, reqwest::Error>)>(100);
-    let semaphore = Arc::new(Semaphore::new(args.simultaneous_requests));
-    let rx = Arc::new(Mutex::new(rx));
-
-    for q in query.clone() {
-        tx.send(q).await.map_err(|e| format!("Failed to send query: {}", e))?;
-    }
-
-    let proxies = get_lines(&args.proxies.as_deref().unwrap())
-        .map_err(|e| format!("Failed to load proxies: {}", e))?;
-    if proxies.is_empty() {
-        logger.err("no proxies were found", true);
-        return Err("No proxies found".to_string());
-    }
-
-    if args.workers == 0 {
-        return Err("Number of workers must be at least 1".to_string());
-    }
-
     if args.workers > 5 {
         logger.warn(
             "Using more than 5 workers may increase RAM usage",
             true,
         );
-        let input = logger.input("Do you want to continue? [Y/n]").to_lowercase();
-        if !input.starts_with('y') && !input.is_empty() {
+        if !logger.confirm("Do you want to continue?", true) {
             return Err("User interrupted".to_string());
         }
     }
 
-    for i in 0..args.workers {
-        let worker_tx = tx.clone();
-        let worker_rx = rx.clone();
-        let worker_log_tx = log_tx.clone();
-        let worker_result_tx = result_tx.clone();
-        let worker_semaphore = semaphore.clone();
-        let user_agent_str = user_agent.get_random();
-        let proxies_clone = proxies.clone();
-
-        tokio::spawn(async move {
-            worker(
-                i,
-                proxies_clone,
-                &user_agent_str,
-                worker_rx,
-                worker_tx,
-                worker_log_tx,
-                worker_result_tx,
-                worker_semaphore,
-            )
-            .await;
-        });
-    }
-
-    let logger_for_log = Arc::clone(logger);
-    tokio::spawn(async move {
-        while let Some(log) = log_rx.recv().await {
-            logger_for_log.req(&log, false);
-        }
-    });
-
-    let logger_for_result = Arc::clone(logger);
-    let target_for_save = target.to_string();
-    let output_path = args.output_path.clone();
-    tokio::spawn(async move {
-        while let Some((url, result)) = result_rx.recv().await {
-            match result {
-                Ok(res) if res.status().is_success() => {
-                    logger_for_result.res(&format!("Status {:?} for {}", res.status(), url), true);
-                    let text = res.text().await.unwrap_or_default();
-                    let parsed = parse(&text);
-                    if parsed.is_empty() {
-                        logger_for_result.nfnd(&format!("No results found for {}", url), true);
-                        continue;
-                    }
-                    for (title, link, description) in parsed {
-                        if !title.is_empty() && !link.is_empty() && !description.is_empty() {
-                            logger_for_result.fnd(
-                                &format!("{} - {} ({})", title, description, link),
-                                true,
-                            );
-                            save_results(&logger_for_result, &target_for_save, &vec![(title.clone(), link.clone(), description.clone())], output_path.as_deref())
-                                .unwrap_or_else(|e| {
-                                    logger_for_result.err(&format!("Failed to save results: {}", e), true);
-                                });
-                        }
-                    }
-                }
-                Ok(res) => {
-                    let status = res.status();
-                    let url = res.url().clone();
-                    logger_for_result.nfnd(&format!("No results for {} => {}", url, status), true);
-                }
-
-                Err(e) => {
-                    logger_for_result.err(&format!("Request failed for {}: {}", url, e), true);
-                }
-            }
-        }
-    });
+    logger.inf("starting workers...", false);
+    let scanner = Scanner::new()
+        .targets(targets.iter().cloned())
+        .queries(queries)
+        .engines(args.engines.clone())
+        .concurrency(args.simultaneous_requests)
+        .workers(args.workers)
+        .proxies(proxies)
+        .rotation(args.rotate)
+        .retry(RetryPolicy {
+            max_retries: args.retries,
+            backoff: Duration::from_millis(args.backoff),
+        })
+        .timeout(Duration::from_millis(args.timeout))
+        .rate_limit(rate_limiter(args))
+        .pages(args.pages as usize)
+        .per_page(args.results)
+        .user_agents(user_agents)
+        .output(args.format, output.clone())
+        .logger(Arc::clone(logger));
+    tokio::spawn(show_progress(Arc::clone(logger), scanner.subscribe()));
+    let scan = scanner.search()?;
+    let summary = consume(scan, logger, Mode::Dork, targets.len() > 1).await;
 
-    tokio::signal::ctrl_c().await.unwrap();
-    logger.inf("Received Ctrl+C, shutting down...", true);
-    let duration = start_time.elapsed();
     logger.inf(
         &format!(
             "average {:.2} requests/min completed in {}",
-            query.len() as f64 / duration.as_secs_f64(),
-            time_format(duration.as_secs()),
+            summary.requests as f64 * 60.0 / summary.duration.as_secs_f64(),
+            time_format(summary.duration.as_secs()),
+        ),
+        true,
+    );
+    logger.inf(
+        &format!(
+            "{} page(s) done, {} abandoned, {} cancelled",
+            summary.done,
+            summary.abandoned,
+            summary.cancelled
+        ),
+        true,
+    );
+    logger.inf(
+        &format!(
+            "responses: {}",
+            summary
+                .responses
+                .iter()
+                .map(|(kind, count)| format!("{} {}", count, kind))
+                .collect::<Vec<_>>()
+                .join(", ")
         ),
         true,
     );
+    if let Some(health) = &summary.pool {
+        logger.inf(
+            &format!(
+                "proxy pool: {} used of {}, {} healthy, {} quarantined, {} unusable ({} successes, {} failures, {} captchas)",
+                health.used,
+                health.total,
+                health.healthy,
+                health.quarantined,
+                health.unusable,
+                health.successes,
+                health.failures,
+                health.captchas
+            ),
+            true,
+        );
+        for (proxy, successes, latency) in &health.best {
+            logger.dbg(&format!("{} => {} success(es), {} ms average", proxy, successes, latency.as_millis()), false);
+        }
+    }
     logger.inf("All tasks completed!", true);
     Ok(())
 }
 
-#[derive(Debug)]
-enum LogMessage {
-    Request(String),
-    Response(String),
-}
-
 async fn run_api_mode(
     args: &Cli,
-    target: &str,
+    targets: &[String],
+    output: &OutputOptions,
     logger: &Arc<Logger>,
-    user_agent: &RandomUserAgent,
+    user_agents: Vec<String>,
 ) -> Result<(), String> {
     if args.simultaneous_requests > 5 {
         logger.warn(
             "Using more than 5 simultaneous requests may increase RAM usage",
             true,
         );
-        let input = logger.input("Do you want to continue? [Y/n]").to_lowercase();
-        if !input.starts_with('y') && !input.is_empty() {
+        if !logger.confirm("Do you want to continue?", true) {
             return Err("User interrupted".to_string());
         }
     }
@@ -347,181 +961,375 @@ async fn run_api_mode(
     }
 
     logger.inf(&format!("loading sites from {}...", args.api_sites), false);
-    let sites = get_lines(&args.api_sites).map_err(|e| format!("Failed to load sites: {}", e))?;
+    let (sites, rejected) = sites::load(&args.api_sites).map_err(|e| format!("Failed to load sites: {}", e))?;
+    for message in &rejected {
+        logger.warn(&format!("Skipping invalid site at {}", message), false);
+    }
+    if !rejected.is_empty() {
+        logger.warn(&format!("{} invalid site(s) were skipped", rejected.len()), true);
+    }
     if sites.is_empty() {
         logger.err("no sites were found", true);
         return Err("No sites found".to_string());
     }
-
     logger.dbg(&format!("{} site(s) were loaded", sites.len()), true);
-    logger.inf("creating client...", false);
-    let client = Client::builder()
-        .tcp_keepalive(Some(Duration::from_secs(15)))
-        .tcp_nodelay(true)
-        .tcp_keepalive_interval(Some(Duration::from_secs(15)))
-        .tcp_keepalive_retries(3)
-        .build()
-        .map_err(|e| format!("Failed to build reqwest client: {}", e))?;
-
-    let manager = ApiMode::new(target.to_string());
-    let builds = sites
-        .iter()
-        .map(|site| {
-            let client_ = client.clone();
-            manager.build(site, &client_, user_agent.get_random())
-        })
-        .collect::<Vec<Request>>();
-    logger.dbg(&format!("{} build(s) were loaded", builds.len()), true);
 
     logger.inf("starting requests...", false);
-    let start_time = Instant::now();
-    let semaphore = Arc::new(Semaphore::new(args.simultaneous_requests));
-
-    let (log_tx, mut log_rx) = mpsc::channel::<LogMessage>(100);
-    let logger_for_logs = Arc::clone(logger);
+    let scanner = Scanner::new()
+        .targets(targets.iter().cloned())
+        .sites(sites)
+        .concurrency(args.simultaneous_requests)
+        .timeout(Duration::from_millis(args.timeout))
+        .rate_limit(rate_limiter(args))
+        .user_agents(user_agents)
+        .output(args.format, output.clone())
+        .logger(Arc::clone(logger));
+    tokio::spawn(show_progress(Arc::clone(logger), scanner.subscribe()));
+    let scan = scanner.scan_profiles()?;
+    let summary = consume(scan, logger, Mode::Api, targets.len() > 1).await;
 
-    tokio::spawn(async move {
-        while let Some(log) = log_rx.recv().await {
-            match log {
-                LogMessage::Request(msg) => logger_for_logs.req(&msg, true),
-                LogMessage::Response(msg) => logger_for_logs.res(&msg, true),
-            }
-        }
-    });
-
-    let results: Vec<_> = stream::iter(builds.into_iter().map(|build| {
-        let client_ = client.clone();
-        let log_tx = log_tx.clone();
-        let sem = Arc::clone(&semaphore);
-        async move {
-            let _permit = sem.acquire().await.unwrap();
-            let url = build.url().to_string();
-            let _ = log_tx
-                .send(LogMessage::Request(format!("Sending request to {}", url)))
-                .await
-                .map_err(|e| eprintln!("Failed to send log: {}", e));
-
-            let result = exec(&client_, build).await;
-            let _ = log_tx
-                .send(LogMessage::Response(match &result {
-                    Ok(res) => format!("Received response for {} with status: {}", url, res.status()),
-                    Err(e) => format!("Error receiving response for {}: {}", url, e),
-                }))
-                .await
-                .map_err(|e| eprintln!("Failed to send log: {}", e));
-
-            result
-        }
-    }))
-    .buffer_unordered(args.simultaneous_requests)
-    .collect()
-    .await;
-
-    let duration = start_time.elapsed();
+    if summary.requests == 0 {
+        logger.err("no responses were received", true);
+        return Err("No responses received".to_string());
+    }
     logger.inf(
         &format!(
             "average {:.2} requests/min completed in {}",
-            results.len() as f64 / duration.as_secs_f64(),
-            time_format(duration.as_secs())
+            summary.requests as f64 * 60.0 / summary.duration.as_secs_f64(),
+            time_format(summary.duration.as_secs())
         ),
         true,
     );
-
-    logger.inf("processing responses...", false);
-    if results.is_empty() {
-        logger.err("no responses were received", true);
-        return Err("No responses received".to_string());
+    if summary.cancelled > 0 {
+        logger.warn(&format!("{} request(s) cancelled", summary.cancelled), true);
     }
+    logger.inf("All tasks completed!", true);
 
-    logger.dbg(&format!("{} responses loaded", results.len()), false);
-    let mut found_urls = Vec::new();
-    for result in results {
-        match result {
-            Ok(res) if res.status().is_success() => {
-                let url = res.url().clone();
-                let status = res.status();
-                logger.fnd(
-                        &format!("Results found for {} => \x1b[35;1m{}\x1b[0m", url, status),
-                        true,
+    Ok(())
+}
+
+/// Logs the events of a scan until it finishes, cancelling it on Ctrl+C.
+async fn consume(mut scan: Scan, logger: &Logger, mode: Mode, several: bool) -> Summary {
+    let prefix = |target: &str| if several { format!("[{}] ", target) } else { String::new() };
+    let mut rejected = 0;
+    let mut cancelled = false;
+    loop {
+        let event = tokio::select! {
+            event = scan.next() => match event {
+                Some(event) => event,
+                None => return Summary::default(),
+            },
+            _ = tokio::signal::ctrl_c(), if !cancelled => {
+                logger.inf("Received Ctrl+C, cancelling...", true);
+                scan.cancel();
+                cancelled = true;
+                continue;
+            }
+        };
+
+        match event {
+            Event::SitesSkipped { target, sites } => {
+                logger.warn(
+                    &format!(
+                        "{} is not a valid username on {} site(s): {}",
+                        target,
+                        sites.len(),
+                        sites.iter().map(|(site, reason)| format!("{} ({})", site, reason)).collect::<Vec<_>>().join(", ")
+                    ),
+                    false,
                 );
-                found_urls.push(url.to_string());
+                rejected += sites.len();
+            }
+            Event::SiteInvalid { target, site, error } => {
+                logger.warn(&format!("Skipping {} for {}: {}", site, target, error), true);
             }
-            Ok(res) => {
-                let url = res.url().clone();
-                let status = res.status();
-                logger.nfnd(
-                    &format!("No results for {} => {}", url, status),
+            Event::RequestSent { url, proxy: None, .. } => logger.req(&format!("Sending request to {}", url), true),
+            Event::RequestSent { url, proxy: Some(proxy), .. } => {
+                logger.req(&format!("Sending request to {} through {}", url, proxy), false)
+            }
+            Event::ResponseReceived(meta) if mode == Mode::Api => match meta.status {
+                Some(status) => logger.res(&format!("Received response for {} with status: {}", meta.url, status), true),
+                None => logger.res(&format!("Error receiving response for {}: {}", meta.url, meta.outcome), true),
+            },
+            Event::ResponseReceived(_) | Event::Progress { .. } | Event::Stopped => {}
+            Event::Finding(finding) => match mode {
+                Mode::Api => {
+                    let url = &finding.url;
+                    let status = finding.status.map(|s| s.to_string()).unwrap_or_else(|| "no response".to_string());
+                    let rule = finding.reason.as_deref().unwrap_or_default();
+                    match finding.verdict {
+                        Some(Verdict::Found) => logger.fnd(
+                            &format!("Results found for {} => \x1b[35;1m{}\x1b[0m ({})", url, status, rule),
+                            true,
+                        ),
+                        Some(Verdict::NotFound) => logger.nfnd(&format!("No results for {} => {} ({})", url, status, rule), true),
+                        _ => logger.warn(&format!("Uncertain result for {} => {} ({})", url, status, rule), true),
+                    }
+                }
+                Mode::Dork => logger.fnd(
+                    &format!(
+                        "{}{} - {} ({})",
+                        prefix(&finding.target),
+                        finding.title.as_deref().unwrap_or_default(),
+                        finding.snippet.as_deref().unwrap_or_default(),
+                        finding.url
+                    ),
                     true,
-                );
+                ),
+            },
+            Event::PageEmpty { target, engine, query, page, seen } => logger.nfnd(
+                &format!(
+                    "{}No {}results found on {} for {} (page {})",
+                    prefix(&target),
+                    if seen { "new " } else { "" },
+                    engine,
+                    query,
+                    page + 1
+                ),
+                true,
+            ),
+            Event::PageFetched { worker, proxy, url, kind } => {
+                logger.req(&format!("[#{} => {}] Successfully fetched {} ({})", worker, proxy, url, kind), false)
+            }
+            Event::ProxyFailed { worker, proxy, url, reason } => {
+                logger.req(&format!("[#{} => {}] Failed to fetch {} ({})", worker, proxy, url, reason), false)
             }
-            Err(e) => {
-                logger.res(&format!("Error occurred: {}", e), true);
+            Event::QueryRetried { url, attempt, delay, .. } => {
+                logger.dbg(&format!("Retrying {} in {} ms (attempt {})", url, delay.as_millis(), attempt + 1), false)
+            }
+            Event::QueryAbandoned { url, reason, .. } => logger.req(&format!("Giving up on {}: {}", url, reason), false),
+            Event::Error(message) => logger.err(&message, true),
+            Event::TargetFinished(summary) => {
+                if rejected > 0 {
+                    logger.warn(&format!("{} site/target pair(s) skipped, the username is not valid there", rejected), true);
+                    rejected = 0;
+                }
+                show_target(logger, mode, several, &summary);
+            }
+            Event::RunFinished(summary) => return *summary,
+        }
+    }
+}
+
+/// Logs how far a scan is, every tenth of the way.
+async fn show_progress(logger: Arc<Logger>, mut events: Subscription) {
+    let mut shown = 0;
+    while let Some(event) = events.recv().await {
+        match event {
+            Event::Progress { done, remaining } => {
+                let total = done + remaining;
+                let tenths = (done * 10).checked_div(total).unwrap_or(10);
+                if tenths > shown {
+                    shown = tenths;
+                    logger.inf(&format!("progress: {}/{} ({}%)", done, total, done * 100 / total.max(1)), false);
+                }
             }
+            Event::RunFinished(_) => break,
+            _ => {}
         }
     }
+}
+
+fn show_target(logger: &Logger, mode: Mode, several: bool, summary: &TargetSummary) {
+    match mode {
+        Mode::Api if several => logger.inf(
+            &format!(
+                "{} => {} found, {} not found, {} uncertain",
+                summary.target, summary.found, summary.not_found, summary.uncertain
+            ),
+            true,
+        ),
+        Mode::Api if summary.uncertain > 0 => {
+            logger.warn(&format!("{} site(s) gave an uncertain result", summary.uncertain), true)
+        }
+        Mode::Dork if several => logger.inf(&format!("Summary for {}", summary.target), true),
+        _ => {}
+    }
+    if let Some((path, written)) = &summary.results {
+        logger.inf(&format!("{} result(s) saved to {}", written, path.display()), true);
+    }
+    for (engine, query, page) in &summary.dead_letters {
+        logger.dbg(&format!("[{}] {} abandoned at page {}", engine, query, page + 1), false);
+    }
+    if let Some(path) = &summary.dead_letters_path {
+        logger.warn(
+            &format!(
+                "{} query(ies) exhausted their retries, re-run them from their first page with --queries {}",
+                summary.dead_letters.len(),
+                path.display()
+            ),
+            true,
+        );
+    }
+    for (engine, query, pages, links) in &summary.pages {
+        logger.inf(&format!("[{}] {} => {} page(s), {} link(s)", engine, query, pages, links), false);
+    }
+}
 
-    save_results_simple(&logger, &args.target, &found_urls, args.output_path.as_deref()).unwrap_or_else(|e| {
-        logger.err(&format!("Failed to save results: {}", e), true);
-    });
+/// Where the configuration came from, for `enola config show`.
+struct Configured {
+    path: Option<PathBuf>,
+    profile: Option<String>,
+    matches: ArgMatches,
+    sources: BTreeMap<String, Source>,
+}
 
-    logger.inf("All tasks completed!", true);
+/// Parses the command line, filling the options it leaves out from the
+/// environment, then from the configuration profile.
+fn configure() -> Result<(Cli, Configured), String> {
+    let command = Cli::command();
+    let raw: Vec<OsString> = env::args_os().collect();
+    let lenient = command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(&raw)
+        .unwrap_or_else(|e| e.exit());
+
+    let explicit = lenient
+        .get_one::<PathBuf>("config")
+        .cloned()
+        .or_else(|| env::var_os("ENOLA_CONFIG").map(PathBuf::from));
+    let path = explicit.clone().or_else(config::config_path);
+    let settings = match &path {
+        Some(path) => config::load(path, explicit.is_some())?,
+        None => Config::default(),
+    };
+    let name = lenient
+        .get_one::<String>("profile")
+        .cloned()
+        .or_else(|| env::var("ENOLA_PROFILE").ok());
+    let profile = settings.profile(name.as_deref())?;
+
+    let (args, sources) = config::apply(&command, &lenient, raw, profile, |var| env::var(var).ok())?;
+    let matches = command.get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    Ok((
+        cli,
+        Configured {
+            path: path.filter(|path| path.exists()),
+            profile: profile.map(|(name, _)| name.to_string()),
+            matches,
+            sources,
+        },
+    ))
+}
+
+fn run_config_show(configured: &Configured, logger: &Logger) -> Result<(), String> {
     logger.inf(
         &format!(
-            "Results saved to {}/results/{}.txt",
-            current_dir().unwrap().display(),
-            target.replace("/", "_")
+            "Configuration file: {}",
+            configured.path.as_ref().map_or("none".to_string(), |path| path.display().to_string())
         ),
         true,
     );
-
+    logger.inf(&format!("Profile: {}", configured.profile.as_deref().unwrap_or("none")), true);
+    for (name, value, source) in config::effective(&Cli::command(), &configured.matches, &configured.sources) {
+        logger.inf(&format!("{} = {} ({})", name, value, source), false);
+    }
     Ok(())
 }
 
 #[tokio::main]
 async fn main() {
-    let args = Cli::parse();
-    let logger = Arc::new(Logger::new(LogLevel::from(args.verbose)));
-    let target = args.target.clone();
+    let (args, configured) = match configure() {
+        Ok(configured) => configured,
+        Err(e) => {
+            Logger::new(LogLevel::Error).err(&format!("Error during execution: {}", e), true);
+            std::process::exit(1);
+        }
+    };
+    let prompts = if args.yes {
+        Prompts::Yes
+    } else if args.no_input || !io::stdin().is_terminal() {
+        Prompts::Defaults
+    } else {
+        Prompts::Ask
+    };
+    let logger = Arc::new(Logger::new(LogLevel::from(args.verbose)).with_prompts(prompts));
 
-    let (exists, file) = is_results_exists(&logger, &target, args.output_path.as_deref());
+    if let Some(command) = &args.command {
+        let result = match command {
+            Command::Proxy { action: ProxyCommand::Check(check) } => run_proxy_check(check, &logger).await,
+            Command::Diff(diff) => run_diff(diff, &logger),
+            Command::Query(query) => run_query(query, &logger),
+            Command::Report(report) => run_report(report, &logger),
+            Command::Config { action: ConfigCommand::Show } => run_config_show(&configured, &logger),
+        };
+        if let Err(e) = result {
+            logger.err(&format!("Error during execution: {}", e), true);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    if exists {
-        logger.warn(
-            &format!(
-                "Results for {} already exists in {}",
-                target,
-                file.display()
-            ),
-            true,
-        );
-        let input = logger.input("Do you want to continue? [Y/n]").to_lowercase();
-        if !input.starts_with('y') && !input.is_empty() {
-            logger.err("User interrupted", false);
-            return;
+    let targets = match load_targets(&args) {
+        Ok(targets) => targets,
+        Err(e) => {
+            logger.err(&format!("Error during execution: {}", e), true);
+            std::process::exit(1);
+        }
+    };
+
+    let output = OutputOptions {
+        output_file: args.output_path.clone(),
+        output_dir: args.output_dir.clone(),
+        database: args.sqlite.clone().map(|path| path.unwrap_or_else(|| database_path(&logger))),
+        report: args.report,
+        run_id: new_run_id(),
+    };
+    #[cfg(not(feature = "sqlite"))]
+    if output.database.is_some() {
+        logger.err(NO_SQLITE, true);
+        std::process::exit(1);
+    }
+    if targets.len() > 1 && output.is_single_file() {
+        logger.err("--output names a single file, use --output-dir with several targets", true);
+        std::process::exit(1);
+    }
+
+    let mut exists = false;
+    for target in &targets {
+        let (target_exists, file) = is_results_exists(&logger, target, args.format, &output);
+        if target_exists {
+            logger.warn(
+                &format!(
+                    "Results for {} already exists in {}",
+                    target,
+                    file.display()
+                ),
+                true,
+            );
+            exists = true;
         }
     }
 
+    if exists && !logger.confirm_or("Do you want to overwrite them?", true, false) {
+        logger.err("User interrupted", true);
+        std::process::exit(1);
+    }
+    if targets.len() > 1 {
+        logger.inf(&format!("{} targets: {}", targets.len(), targets.join(", ")), true);
+    }
+
     logger.inf(&format!("loading user-agents from {}...", args.user_agent_list), false);
     let user_agents = get_lines(&args.user_agent_list).unwrap_or_default();
     if user_agents.is_empty() {
         logger.warn("no user-agents were found", true);
-        let input = logger.input("Do you want to continue? [Y/n]").to_lowercase();
-        if !input.starts_with('y') && !input.is_empty() {
-            logger.err("User interrupted", false);
-            return;
+        if !logger.confirm("Do you want to continue with the default user-agent?", true) {
+            logger.err("User interrupted", true);
+            std::process::exit(1);
         }
     }
-    let user_agent = RandomUserAgent::new(user_agents);
 
     match (args.proxies.is_some(), args.google_dork_mode) {
         (true, true) => {
-            if let Err(e) = run_proxy_mode(&args, &target, &logger, &user_agent).await {
+            if let Err(e) = run_proxy_mode(&args, &targets, &output, &logger, user_agents).await {
                 logger.err(&format!("Error during execution: {}", e), true);
                 std::process::exit(1);
             }
         },
         _ => {
-            if let Err(e) = run_api_mode(&args, &target, &logger, &user_agent).await {
+            if let Err(e) = run_api_mode(&args, &targets, &output, &logger, user_agents).await {
                 logger.err(&format!("Error during execution: {}", e), true);
                 std::process::exit(1);
             }